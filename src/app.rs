@@ -0,0 +1,283 @@
+/* State of the program.
+ *
+ * The keyboard thread sends Events through a channel, and the render thread gives them to the App,
+ * that changes its State. The screens are named (there are no numbers to keep in order), so to add
+ * a new screen it is enough to add it to View and to the menu (in main_menu), and to draw it.
+ * */
+
+use termion::event::{Key, MouseButton, MouseEvent};
+
+use crate::menu::Menu;
+
+/*
+ *  what the render thread receives from the keyboard thread (the keys and the mouse). Closed means
+ *  that there is nothing more to read.
+ * */
+#[derive(Debug, Clone, PartialEq)]
+pub enum Event {
+    Key(Key),
+    Mouse(MouseEvent),
+    Closed,
+}
+
+/*
+ *  the screens with figures.
+ * */
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum View {
+    Things,
+    Cube,
+    Pyramid,
+    Ruby,
+    Model,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum State {
+    Menu,
+    View(View),
+    Exit,
+}
+
+/*
+ *  what the keys and the mouse ask to do to the Figure of a View: turn it (the angles around x and
+ *  around y), move the camera to it (or away if the distance is negative), or go back to how it
+ *  started. Drag and Orbit say how many cells the mouse moved while a button was pressed, with the
+ *  left button the Figure turns (or the camera, if the View has not one Figure) and with the right
+ *  one the camera goes around.
+ * */
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Command {
+    Rotate { x: f64, y: f64 },
+    Drag { dx: f64, dy: f64 },
+    Orbit { dx: f64, dy: f64 },
+    Zoom(f64),
+    Reset,
+}
+
+/*
+ *  what happens when an entry of the menu is chosen.
+ * */
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Action {
+    Open(View),
+    Exit,
+}
+
+#[derive(Debug)]
+pub struct App {
+    _state: State,
+
+    // the main menu, it keeps the selected entry while a View is shown.
+    _menu: Menu<Action>,
+
+    // if the Figures of the Views don't turn by themselves.
+    _paused: bool,
+
+    // the button that is pressed and the last cell where the mouse was.
+    _drag: Option<(MouseButton, u16, u16)>,
+}
+
+/*
+ *  the keys that end the program in every screen.
+ * */
+static QUIT_KEYS: &[Key] = &[Key::Char('q'), Key::Esc, Key::Ctrl('c')];
+
+/*
+ *  how much the Figures turn (in radians) and the camera moves with each key.
+ * */
+static ROTATE_STEP: f64 = 0.1f64;
+static ZOOM_STEP: f64 = 5f64;
+
+/*
+ *  struct implementation.
+ * */
+impl Default for App {
+    fn default() -> App {
+        App::get_new()
+    }
+}
+
+impl App {
+    /* the program starts in the menu, with the first entry selected. */
+    pub fn get_new() -> App {
+        App { _state: State::Menu, _menu: main_menu(), _paused: false, _drag: None }
+    }
+
+    pub fn get_state(&self) -> State {
+        self._state
+    }
+
+    pub fn get_menu(&self) -> &Menu<Action> {
+        &self._menu
+    }
+
+    pub fn get_menu_mut(&mut self) -> &mut Menu<Action> {
+        &mut self._menu
+    }
+
+    pub fn is_running(&self) -> bool {
+        self._state != State::Exit
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self._paused
+    }
+
+    /* change the State with the Event. Everywhere q, Esc and Ctrl-C end the program, in the menu
+     * Up and Down move the selection, Enter chooses the entry, Right opens a submenu and Left (or
+     * Backspace) closes it. In the Views Enter goes back to the menu, space stops or resumes the
+     * rotation (in the Views with one Figure), and the other keys (and the mouse) give the Command
+     * to do with the Figure: the arrows (or WASD) turn it, + and - zoom, and r resets it. */
+    pub fn handle(&mut self, event: Event) -> Option<Command> {
+        let key = match event {
+            Event::Key(key) => key,
+            Event::Mouse(mouse) => return self.handle_mouse(mouse),
+            Event::Closed => {
+                self._state = State::Exit;
+                return None;
+            },
+        };
+        if QUIT_KEYS.contains(&key) {
+            self._state = State::Exit;
+            return None;
+        }
+        match self._state {
+            State::Menu => match key {
+                Key::Char('\n') => match self._menu.choose() {
+                    Some(Action::Open(view)) => {
+                        // each View starts turning, even if the last one was paused.
+                        self._state = State::View(view);
+                        self._paused = false;
+                    },
+                    Some(Action::Exit) => self._state = State::Exit,
+                    None => {},
+                },
+                Key::Right => self._menu.open(),
+                Key::Left | Key::Backspace => {
+                    self._menu.back();
+                },
+                Key::Up => self._menu.up(),
+                Key::Down => self._menu.down(),
+                _ => {},
+            },
+            State::View(_) => match key {
+                Key::Char('\n') => {
+                    self._state = State::Menu;
+                },
+                // the Figures of "things" are moved by the scene, they can't be paused.
+                Key::Char(' ') if self._state != State::View(View::Things) => {
+                    self._paused = !self._paused;
+                },
+                Key::Up | Key::Char('w') => return Some(Command::Rotate { x: -ROTATE_STEP, y: 0f64 }),
+                Key::Down | Key::Char('s') => return Some(Command::Rotate { x: ROTATE_STEP, y: 0f64 }),
+                Key::Left | Key::Char('a') => return Some(Command::Rotate { x: 0f64, y: -ROTATE_STEP }),
+                Key::Right | Key::Char('d') => return Some(Command::Rotate { x: 0f64, y: ROTATE_STEP }),
+                Key::Char('+') | Key::Char('=') => return Some(Command::Zoom(ZOOM_STEP)),
+                Key::Char('-') | Key::Char('_') => return Some(Command::Zoom(-ZOOM_STEP)),
+                Key::Char('r') => return Some(Command::Reset),
+                _ => {},
+            },
+            State::Exit => {},
+        }
+        None
+    }
+
+    /* in the Views, moving the mouse with the left or the right button pressed gives a Drag or an
+     * Orbit, and the wheel zooms. */
+    fn handle_mouse(&mut self, mouse: MouseEvent) -> Option<Command> {
+        if !matches!(self._state, State::View(_)) {
+            self._drag = None;
+            return None;
+        }
+        match mouse {
+            MouseEvent::Press(MouseButton::WheelUp, _, _) => Some(Command::Zoom(ZOOM_STEP)),
+            MouseEvent::Press(MouseButton::WheelDown, _, _) => Some(Command::Zoom(-ZOOM_STEP)),
+            MouseEvent::Press(button @ (MouseButton::Left | MouseButton::Right), x, y) => {
+                self._drag = Some((button, x, y));
+                None
+            },
+            MouseEvent::Hold(x, y) => {
+                let (button, last_x, last_y) = self._drag?;
+                self._drag = Some((button, x, y));
+                let (dx, dy) = (x as f64 - last_x as f64, y as f64 - last_y as f64);
+                match button {
+                    MouseButton::Right => Some(Command::Orbit { dx, dy }),
+                    _ => Some(Command::Drag { dx, dy }),
+                }
+            },
+            MouseEvent::Release(_, _) => {
+                self._drag = None;
+                None
+            },
+            _ => None,
+        }
+    }
+}
+
+/*
+ *  functions.
+ * */
+
+/* the entries of the main menu, in order. The Figures alone are in a submenu. */
+fn main_menu() -> Menu<Action> {
+    let mut figures = Menu::get_new("Figures");
+    figures.add("Cube", Action::Open(View::Cube));
+    figures.add("Pyramid", Action::Open(View::Pyramid));
+    figures.add("Ruby", Action::Open(View::Ruby));
+    figures.add("Model", Action::Open(View::Model));
+
+    let mut menu = Menu::get_new("Rotating cube");
+    menu.add("Things", Action::Open(View::Things));
+    menu.add_submenu("Figures", figures);
+    menu.add("Exit", Action::Exit);
+    menu
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn press(app: &mut App, keys: &[Key]) {
+        for &key in keys {
+            app.handle(Event::Key(key));
+        }
+    }
+
+    #[test]
+    fn pause_is_not_kept_by_the_next_view() {
+        let mut app = App::get_new();
+        // Figures > Cube.
+        press(&mut app, &[Key::Down, Key::Char('\n'), Key::Char('\n')]);
+        assert_eq!(app.get_state(), State::View(View::Cube));
+        press(&mut app, &[Key::Char(' ')]);
+        assert!(app.is_paused());
+
+        // the menu is back in the submenu where it was.
+        press(&mut app, &[Key::Char('\n')]);
+        assert_eq!(app.get_menu().get_active().get_title(), "Figures");
+        press(&mut app, &[Key::Down, Key::Char('\n')]);
+        assert_eq!(app.get_state(), State::View(View::Pyramid));
+        assert!(!app.is_paused());
+    }
+
+    #[test]
+    fn things_is_not_paused() {
+        let mut app = App::get_new();
+        press(&mut app, &[Key::Char('\n'), Key::Char(' ')]);
+        assert_eq!(app.get_state(), State::View(View::Things));
+        assert!(!app.is_paused());
+    }
+
+    #[test]
+    fn quit_keys() {
+        for key in QUIT_KEYS {
+            let mut app = App::get_new();
+            press(&mut app, &[*key]);
+            assert!(!app.is_running());
+        }
+        let mut app = App::get_new();
+        app.handle(Event::Closed);
+        assert_eq!(app.get_state(), State::Exit);
+    }
+}
@@ -0,0 +1,118 @@
+/* Clock of the render loop.
+ *
+ * The FrameClock measures the time between frames (so the animations can move the same in every
+ * computer), waits if the frame was faster than the target frames per second, and keeps the
+ * times of the last frames to show the statistics.
+ * */
+
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+/*
+ *  how many frames are used to compute the statistics.
+ * */
+static SAMPLES: usize = 60;
+
+/*
+ *  the time between frames given to the animations is never longer than this, so the Figures
+ *  don't jump after a pause (for example, when the terminal was stopped).
+ * */
+static MAX_DELTA: f64 = 0.25f64;
+
+/*
+ *  structs definition.
+ * */
+#[derive(Debug)]
+pub struct FrameClock {
+    // the minimum time of a frame, None to draw as fast as posible.
+    _period: Option<Duration>,
+    _last: Instant,
+
+    // of the last frames, the time between them and the time spent drawing them (in seconds).
+    _deltas: VecDeque<f64>,
+    _works: VecDeque<f64>,
+}
+
+/*
+ *  struct implementation.
+ * */
+impl FrameClock {
+    /* a clock that allows at most the frames per second given (0 for no limit). */
+    pub fn get_new(fps: f64) -> FrameClock {
+        let mut clock = FrameClock {
+            _period: None,
+            _last: Instant::now(),
+            _deltas: VecDeque::with_capacity(SAMPLES),
+            _works: VecDeque::with_capacity(SAMPLES),
+        };
+        clock.set_target_fps(fps);
+        clock
+    }
+
+    pub fn get_target_fps(&self) -> f64 {
+        self._period.map(|p| 1f64 / p.as_secs_f64()).unwrap_or(0f64)
+    }
+
+    pub fn set_target_fps(&mut self, fps: f64) {
+        self._period = if fps > 0f64 { Some(Duration::from_secs_f64(1f64 / fps)) } else { None };
+    }
+
+    /* called once by frame, it waits until the frame has lasted the time of the target frames per
+     * second and returns the seconds since the last call. */
+    pub fn tick(&mut self) -> f64 {
+        let work = self._last.elapsed();
+        if let Some(period) = self._period {
+            if work < period {
+                std::thread::sleep(period - work);
+            }
+        }
+        let now = Instant::now();
+        let delta = (now - self._last).as_secs_f64();
+        self._last = now;
+
+        if self._deltas.len() == SAMPLES {
+            self._deltas.pop_front();
+            self._works.pop_front();
+        }
+        self._deltas.push_back(delta);
+        self._works.push_back(work.as_secs_f64());
+        delta.min(MAX_DELTA)
+    }
+
+    /* frames per second of the last frames. */
+    pub fn get_fps(&self) -> f64 {
+        let total: f64 = self._deltas.iter().sum();
+        if total > 0f64 { self._deltas.len() as f64 / total } else { 0f64 }
+    }
+
+    /* the mean and the maximum time spent drawing the last frames (in seconds). */
+    pub fn get_frame_time(&self) -> (f64, f64) {
+        if self._works.is_empty() {
+            return (0f64, 0f64);
+        }
+        let total: f64 = self._works.iter().sum();
+        let max = self._works.iter().cloned().fold(0f64, f64::max);
+        (total / self._works.len() as f64, max)
+    }
+
+    /* the statistics in one line, to write them in the Window. */
+    pub fn stats(&self) -> String {
+        let (mean, max) = self.get_frame_time();
+        format!("{:.1} fps  {:.1} ms (max {:.1} ms)", self.get_fps(), mean * 1000f64, max * 1000f64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn target_fps() {
+        let mut clock = FrameClock::get_new(50f64);
+        assert!((clock.get_target_fps() - 50f64).abs() < 1e-6);
+        clock.set_target_fps(0f64);
+        assert_eq!(clock.get_target_fps(), 0f64);
+        // without frames there are no statistics.
+        assert_eq!((clock.get_fps(), clock.get_frame_time()), (0f64, (0f64, 0f64)));
+    }
+}
@@ -0,0 +1,262 @@
+/* Keyboard thread.
+ *
+ * The thread waits for the keys (and the events of the mouse) with a timeout, so it never stays
+ * blocked: between the waits it checks if the program is still running, and it ends soon after
+ * the flag is cleared. The keys and the mouse are sent to the render thread as Events, and when
+ * there is nothing more to read it sends Closed and ends.
+ * */
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::Sender;
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use termion::event::{parse_event, Key};
+
+use crate::app::Event;
+
+/*
+ *  how long the thread waits for a key before checking the flag again (in milliseconds).
+ * */
+static TIMEOUT: i32 = 50;
+
+/*
+ *  the keys are readed in pieces of this size. When many keys arrive together a sequence can be
+ *  cut at the end of a piece, its beginning is kept until the rest arrives.
+ * */
+static READ_SIZE: usize = 64;
+
+/*
+ *  how long the thread waits for the rest of a sequence that arrived cut (in milliseconds). If
+ *  nothing more arrives, an escape byte alone was the Esc key.
+ * */
+static SEQUENCE_TIMEOUT: i32 = 20;
+
+/*
+ *  the byte of the Esc key, that also begins the sequences of the other keys.
+ * */
+static ESC: u8 = 0x1b;
+
+/*
+ *  functions.
+ * */
+
+/* start the thread, it runs while the flag is true. */
+pub fn spawn(sender: Sender<Event>, running: Arc<AtomicBool>) -> JoinHandle<()> {
+    thread::spawn(move || {
+        let mut buf = [0u8; READ_SIZE];
+        // the beginning of a sequence that was cut at the end of the last read.
+        let mut pending: Vec<u8> = Vec::new();
+        while running.load(Ordering::SeqCst) {
+            let timeout = if pending.is_empty() { TIMEOUT } else { SEQUENCE_TIMEOUT };
+            match read_timeout(&mut buf, timeout) {
+                Some(0) => {
+                    let _ = sender.send(Event::Closed);
+                    return;
+                },
+                Some(count) => pending.extend_from_slice(&buf[..count]),
+                None if pending.is_empty() => continue,
+                None => {
+                    // the rest didn't arrive, so an escape byte alone is the Esc key and anything
+                    // else is discarded.
+                    let esc = pending == [ESC];
+                    pending.clear();
+                    if esc && sender.send(Event::Key(Key::Esc)).is_err() {
+                        return;
+                    }
+                    continue;
+                },
+            }
+            match send_events(&pending, &sender) {
+                Some(used) => {
+                    pending.drain(..used);
+                },
+                None => return,
+            }
+            if pending.len() > READ_SIZE {
+                pending.clear();
+            }
+        }
+    })
+}
+
+/* send the Events of the bytes, and return how many bytes were used (the others are the beginning
+ * of a sequence that is not complete). None if the Events can't be sent. */
+fn send_events(bytes: &[u8], sender: &Sender<Event>) -> Option<usize> {
+    let mut pos = 0;
+    while pos < bytes.len() {
+        // termion panics if a sequence ends before its end, so only whole sequences are parsed,
+        // and the ones that it can't parse are discarded.
+        let end = if bytes[pos] == ESC {
+            match sequence_len(&bytes[pos..]) {
+                Some(len) => pos + len,
+                None => return Some(pos),
+            }
+        } else {
+            bytes.len()
+        };
+        if bytes[pos] == ESC && !is_parsable(&bytes[pos..end]) {
+            pos = end;
+            continue;
+        }
+        let mut used = 1;
+        let parsed = {
+            let mut rest = bytes[pos + 1..end].iter().inspect(|_| used += 1).map(|&b| Ok(b));
+            parse_event(bytes[pos], &mut rest)
+        };
+        let event = match parsed {
+            Ok(termion::event::Event::Key(key)) => Some(Event::Key(key)),
+            Ok(termion::event::Event::Mouse(mouse)) => Some(Event::Mouse(mouse)),
+            Ok(_) => None,
+            // it failed at the end of the bytes, the rest of the sequence may not have arrived.
+            Err(_) if pos + used == bytes.len() => return Some(pos),
+            Err(_) => None,
+        };
+        if let Some(event) = event {
+            if sender.send(event).is_err() {
+                return None;
+            }
+        }
+        pos += used;
+    }
+    Some(pos)
+}
+
+/* the length of the escape sequence at the beginning of the bytes, None if it is not complete.
+ * The sequences end like termion expects: the ones with "[" at the first byte from 64 to 126, the
+ * ones of the mouse with "[<" at "m" or "M", and the old ones of the mouse ("[M") three bytes
+ * after. */
+fn sequence_len(bytes: &[u8]) -> Option<usize> {
+    match (bytes.get(1)?, bytes.get(2)) {
+        (b'[', None) => None,
+        (b'[', Some(b'M')) => if bytes.len() >= 6 { Some(6) } else { None },
+        (b'[', Some(b'<')) => bytes[3..].iter().position(|&b| b == b'm' || b == b'M').map(|end| end + 4),
+        // the function keys of the linux console ("[[A").
+        (b'[', Some(b'[')) => if bytes.len() >= 4 { Some(4) } else { None },
+        (b'[', Some(_)) => bytes[2..].iter().position(|&b| (64..=126).contains(&b)).map(|end| end + 3),
+        (b'O', _) => if bytes.len() >= 3 { Some(3) } else { None },
+        _ => Some(2),
+    }
+}
+
+/* if termion can parse the whole escape sequence. It panics with the numbers of some sequences
+ * that are not valid (it unwraps them), and it prints the codes of the keys that it doesn't
+ * know. */
+fn is_parsable(seq: &[u8]) -> bool {
+    if seq.get(1) != Some(&b'[') || seq.len() < 3 {
+        return true;
+    }
+    let last = seq[seq.len() - 1];
+    match seq[2] {
+        // the old mouse, the button is a byte that termion moves to an i8.
+        b'M' => seq.len() == 6 && (32..128).contains(&seq[3]),
+        b'<' => {
+            numbers(&seq[3..seq.len() - 1], u16::MAX as u32).is_some_and(|nums| nums.len() >= 3)
+        },
+        b'0'..=b'9' => {
+            let params = &seq[2..seq.len() - 1];
+            match last {
+                b'M' => numbers(params, u16::MAX as u32).is_some_and(|nums| nums.len() >= 3),
+                b'~' => numbers(params, u8::MAX as u32).is_some_and(|nums| {
+                    nums.len() != 1 || matches!(nums[0], 1..=8 | 11..=15 | 17..=21 | 23..=24)
+                }),
+                b'A' | b'B' | b'C' | b'D' | b'F' | b'H' => {
+                    numbers(params, u8::MAX as u32).is_some()
+                },
+                _ => true,
+            }
+        },
+        _ => true,
+    }
+}
+
+/* the numbers separated by ";", None if one of them is not a number or is bigger than max. */
+fn numbers(params: &[u8], max: u32) -> Option<Vec<u32>> {
+    std::str::from_utf8(params).ok()?
+        .split(';')
+        .map(|n| n.parse::<u32>().ok().filter(|&n| n <= max))
+        .collect()
+}
+
+/* read what is in the standard input, waiting at most the timeout (in milliseconds). None if
+ * nothing arrived, and Some(0) if the input was closed. */
+fn read_timeout(buf: &mut [u8], timeout: i32) -> Option<usize> {
+    let mut fds = libc::pollfd { fd: libc::STDIN_FILENO, events: libc::POLLIN, revents: 0 };
+    // SAFETY: the pointer is to one pollfd that lives during the call.
+    let ready = unsafe { libc::poll(&mut fds, 1, timeout) };
+    if ready <= 0 {
+        // nothing arrived, or the wait was interrupted by a signal.
+        return None;
+    }
+    // SAFETY: the buffer is valid for its length, and poll said that the read won't block.
+    let count = unsafe { libc::read(libc::STDIN_FILENO, buf.as_mut_ptr() as *mut libc::c_void, buf.len()) };
+    if count < 0 {
+        let err = std::io::Error::last_os_error();
+        if err.kind() == std::io::ErrorKind::Interrupted || err.kind() == std::io::ErrorKind::WouldBlock {
+            return None;
+        }
+        return Some(0);
+    }
+    Some(count as usize)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc;
+    use termion::event::{MouseButton, MouseEvent};
+
+    /* the bytes used and the Events sent. */
+    fn events(bytes: &[u8]) -> (usize, Vec<Event>) {
+        let (sender, receiver) = mpsc::channel();
+        let used = send_events(bytes, &sender).unwrap();
+        (used, receiver.try_iter().collect())
+    }
+
+    #[test]
+    fn complete_sequences() {
+        let (used, sent) = events(b"a\x1b[A\x1b[<0;3;4M");
+        assert_eq!(used, 13);
+        assert_eq!(sent, vec![
+            Event::Key(Key::Char('a')),
+            Event::Key(Key::Up),
+            Event::Mouse(MouseEvent::Press(MouseButton::Left, 3, 4)),
+        ]);
+    }
+
+    #[test]
+    fn cut_sequences_are_kept() {
+        for cut in [&b"\x1b"[..], b"\x1b[", b"\x1b[<0;3", b"\x1b[<32;10;12"] {
+            let mut bytes = b"\x1b[B".to_vec();
+            bytes.extend_from_slice(cut);
+            let (used, sent) = events(&bytes);
+            assert_eq!(used, 3, "{:?}", cut);
+            assert_eq!(sent, vec![Event::Key(Key::Down)], "{:?}", cut);
+        }
+    }
+
+    #[test]
+    fn bad_sequences_are_discarded() {
+        let bad: [&[u8]; 6] =
+            [b"\x1b[1M", b"\x1b[5;300~", b"\x1b[<a;1;1M", b"\x1b[99~", b"\x1b[1;999A", b"\x1b[M\x90!!"];
+        for seq in bad {
+            let mut bytes = seq.to_vec();
+            bytes.push(b'q');
+            let expected = (bytes.len(), vec![Event::Key(Key::Char('q'))]);
+            assert_eq!(events(&bytes), expected, "{:?}", seq);
+        }
+        // the good ones of the same kinds.
+        assert_eq!(events(b"\x1b[5~").1, vec![Event::Key(Key::PageUp)]);
+        assert_eq!(events(b"\x1b[1;5A").1, vec![Event::Key(Key::CtrlUp)]);
+        let press = |x, y| vec![Event::Mouse(MouseEvent::Press(MouseButton::Left, x, y))];
+        assert_eq!(events(b"\x1b[32;2;3M").1, press(2, 3));
+        assert_eq!(events(b"\x1b[M !!").1, press(1, 1));
+    }
+
+    #[test]
+    fn the_rest_completes_the_sequence() {
+        let (used, _) = events(b"\x1b[A\x1b[");
+        let mut bytes = b"\x1b[A\x1b[".to_vec().split_off(used);
+        bytes.extend_from_slice(b"A");
+        assert_eq!(events(&bytes), (3, vec![Event::Key(Key::Up)]));
+    }
+}
@@ -1,245 +1,352 @@
 /* Program that rotate Figures.
+ *
+ * Usage: rotating_cube [model.obj] [--scene scene.toml], the model given is showed in the "Model"
+ * option and the scene given is showed in the "Things" option (see scenes/things.toml).
  *
  * If you want to make your own Figure, keep in mind that the Figure will rotate along y, or the
- * same, in the two dimentional plane (z, x). The "camera" of the scream is the plane (x, y), so
- * the Figures would not intersect with it to be properly printed.
+ * same, in the two dimentional plane (z, x). The "camera" of the scream is the plane (x, y), and
+ * in the views with perspective the parts of the Figures nearer than the near plane are clipped.
  * */
 
 use std::thread;
-use std::sync::{RwLock, Arc};
-use k_board::{keyboard::Keyboard, keys::Keys};
-use std::io::{stdout, Write};
-use termion::clear::All; 
-use termion::cursor::Goto;
-use termion;
-use termion::{color, raw::IntoRawMode, cursor::DetectCursorPos};
+use std::f64::consts::{PI, TAU};
+use std::path::Path;
+use std::time::Instant;
+use std::sync::mpsc::{self, TryRecvError};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use termion::color;
 
 mod tools;
+mod obj;
+mod scene;
+mod scene_file;
+mod timeline;
+mod clock;
+mod app;
+mod input;
+mod menu;
 pub use tools::Vector;
 pub use tools::Window;
 pub use tools::Figure;
 pub use tools::Writemodes;
+pub use tools::Projection;
+pub use tools::Camera;
+pub use tools::DrawMode;
+pub use tools::RasterMode;
+pub use tools::OutputMode;
+pub use scene::Scene;
+pub use clock::FrameClock;
+pub use app::App;
+pub use app::Event;
+pub use app::State;
+pub use app::View;
+pub use app::Command;
 
-const OPTS: &[&str] = &["Things", "Cube", "Pyramid", "Ruby", "Exit"]; 
-const CANT_OPT: usize = OPTS.len() - 1;
-const INIT_POS_OPT: f64 = 0.05f64;
-const SPACE_POS_OPT: f64 = 0.1f64;
-const COLOR_WHITE: &'static dyn color::Color = &color::Rgb(255, 255, 255);
+const COLOR_WHITE: color::Rgb = color::Rgb(255, 255, 255);
+const FOCAL: f64 = 60f64;
+const NEAR: f64 = 1f64;
+const TARGET_FPS: f64 = 50f64;
 
 /*
- *  functions.
+ *  the angles that the single figures turn in one second (around x, y and z).
+ * */
+const SPIN: Vector = Vector {x: 3.5f64, y: 10f64, z: 0f64};
+
+const USAGE: &str = "usage: rotating_cube [model.obj] [--scene scene.toml] [--fps max_fps] [--stats] [--full-redraw]";
+
+/*
+ *  the scene of the mode "things", used when no scene file is given.
+ * */
+const THINGS: &str = include_str!("../scenes/things.toml");
+
+/*
+ *  the keys of the views with one figure.
  * */
+const HELP: &str = "arrows/WASD turn  +/- zoom  space pause  r reset  Enter menu";
 
-fn wait(seconds: f64) {
-    std::thread::sleep(std::time::Duration::from_millis((seconds * (1000 as f64)) as u64));
+/*
+ *  the figures of the views with one figure, the model only if a file was given.
+ * */
+struct Figures {
+    _cube: Figure,
+    _pyramid: Figure,
+    _ruby: Figure,
+    _model: Option<Figure>,
 }
 
-fn draw_figures(win: &mut tools::Window, figs: &mut [tools::Figure]) {
-    for fig in figs {
-        win.draw (fig);
+/*
+ *  struct implementation.
+ * */
+impl Figures {
+    fn get_new(model: Option<obj::ObjModel>) -> Figures {
+        let position = Vector {x : 0f64, y : 0f64, z : 90f64};
+        let mut figures = Figures {
+            _cube: Figure::cube(30f64, position, COLOR_WHITE),
+            _pyramid: Figure::pyramid(20f64, position, COLOR_WHITE),
+            _ruby: Figure::ruby(30f64, position, COLOR_WHITE),
+            _model: model.map(|m| m.into_figure(position, COLOR_WHITE)),
+        };
+        figures._cube.set_spin(SPIN);
+        figures._pyramid.set_spin(SPIN);
+        figures._ruby.set_spin(SPIN);
+        if let Some(ref mut fig) = figures._model {
+            fig.set_spin(SPIN);
+        }
+        figures
     }
-}
 
-fn write_options (win: &mut tools::Window, options: &[&str]) {
-    let mut ph: f64 = INIT_POS_OPT;
-    for opt in options {
-        win.write(opt, ph, 0.5f64, Writemodes::Centered, COLOR_WHITE);
-        ph = ph + SPACE_POS_OPT;
+    /* the figure of the view, None if the view has not only one figure. */
+    fn get_mut(&mut self, view: View) -> Option<&mut Figure> {
+        match view {
+            View::Cube => Some(&mut self._cube),
+            View::Pyramid => Some(&mut self._pyramid),
+            View::Ruby => Some(&mut self._ruby),
+            View::Model => self._model.as_mut(),
+            View::Things => None,
+        }
     }
 }
 
-fn read_lock (lock: &Arc<RwLock<usize>>) -> usize {
-    *lock.read().unwrap()
+/*
+ *  functions.
+ * */
+
+/* a camera in the default place that looks to the target. */
+fn camera_to (target: Vector) -> Camera {
+    let mut camera = Camera::default();
+    camera.look_at(target);
+    camera
 }
 
-fn write_lock (lock: &Arc<RwLock<usize>>, new_val: usize) {
-    *lock.write().unwrap() = new_val;
+/* do the Command with the Figure of the View, or with the camera if the View has not one Figure.
+ * Dragging the mouse through all the Window turns a whole turn to the sides, and half a turn up
+ * or down. Without Figure, the camera goes back to home when it is reset. */
+fn apply (command: Command, fig: Option<&mut Figure>, home: Camera, win: &mut tools::Window) {
+    let (width, height) = (win.get_width() as f64, win.get_height() as f64);
+    match (command, fig) {
+        (Command::Rotate { x, y }, Some(fig)) => {
+            fig.rotate_in_x(x);
+            fig.rotate_in_y(y);
+        },
+        (Command::Rotate { x, y }, None) => win.get_camera_mut().orbit(-y, -x),
+        (Command::Drag { dx, dy }, Some(fig)) => {
+            fig.rotate_in_x(dy / height * PI);
+            fig.rotate_in_y(dx / width * TAU);
+        },
+        (Command::Drag { dx, dy }, None) | (Command::Orbit { dx, dy }, _) => {
+            win.get_camera_mut().orbit(-dx / width * TAU, -dy / height * PI);
+        },
+        (Command::Zoom(distance), _) => win.get_camera_mut().dolly(distance),
+        (Command::Reset, Some(fig)) => {
+            fig.reset_transform();
+            win.set_camera(camera_to(fig.get_position()));
+        },
+        (Command::Reset, None) => win.set_camera(home),
+    }
 }
 
 /*
  *  main.
  * */
 fn main() {
-    let modex = Arc::new(RwLock::new(0));
-    let _modex = Arc::clone(&modex);
-    let opti = Arc::new(RwLock::new(0));
-    let _opti = Arc::clone(&opti);
+    // read the arguments (the scene file goes after "--scene", the frames per second after "--fps"
+    // with 0 for no limit, "--stats" shows the frames per second in the scream, and with
+    // "--full-redraw" all the scream is printed in each frame, not only what changed).
+    let mut model_path: Option<String> = None;
+    let mut scene_path: Option<String> = None;
+    let mut target_fps: f64 = TARGET_FPS;
+    let mut show_stats: bool = false;
+    let mut output_mode = OutputMode::Diff;
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        if arg == "--scene" {
+            scene_path = args.next();
+            if scene_path.is_none() {
+                eprintln!("{}", USAGE);
+                std::process::exit(1);
+            }
+        } else if arg == "--fps" {
+            target_fps = match args.next().and_then(|fps| fps.parse::<f64>().ok()) {
+                Some(fps) if fps >= 0f64 => fps,
+                _ => {
+                    eprintln!("{}", USAGE);
+                    std::process::exit(1);
+                },
+            };
+        } else if arg == "--stats" {
+            show_stats = true;
+        } else if arg == "--full-redraw" {
+            output_mode = OutputMode::FullRedraw;
+        } else if model_path.is_none() {
+            model_path = Some(arg);
+        } else {
+            eprintln!("{}", USAGE);
+            std::process::exit(1);
+        }
+    }
+
+    // load the model and the scene before touching the terminal, so the errors are readable.
+    let model = match model_path {
+        Some(path) => match obj::load(&path, 30f64) {
+            Ok(model) => Some(model),
+            Err(err) => {
+                eprintln!("{}: {}", path, err);
+                std::process::exit(1);
+            },
+        },
+        None => None,
+    };
+    let things = match scene_path {
+        Some(path) => scene_file::load(&path).unwrap_or_else(|err| {
+            eprintln!("{}: {}", path, err);
+            std::process::exit(1);
+        }),
+        None => scene_file::parse(THINGS, Path::new("scenes")).expect("the built-in scene is wrong"),
+    };
+
+    // the keyboard thread sends the keys to the render thread, that has the state of the program.
+    // The keyboard thread runs while the flag is true.
+    let (sender, receiver) = mpsc::channel::<Event>();
+    let running = Arc::new(AtomicBool::new(true));
+
+    // create a window (must be mutable), it takes the terminal until it is dropped.
+    let mut win: tools::Window = tools::Window::get_new_term_size();
+    win.set_output_mode(output_mode);
 
     let thread1 = thread::spawn(move || {
-        // create a window (must be mutable)
-        let mut win: tools::Window = tools::Window::get_new_term_size();
-
-        // create figures (the posible figures are cube, ruby and pyramid).
-        let mut figs: [tools::Figure; 7] = [
-            Figure::ruby (
-                15f64, 
-                Vector {
-                    x : 35f64,
-                    y : 10f64,
-                    z : 40f64,
-                }, &color::Rgb(255,0,0)
-            ),
-            Figure::ruby (
-                12f64, 
-                Vector {
-                    x : -60f64,
-                    y : -20f64,
-                    z : 40f64,
-                }, &color::Rgb(255,0,0)
-            ),
-            Figure::ruby (
-                13f64, 
-                Vector {
-                    x : -40f64,
-                    y : 5f64,
-                    z : 20f64,
-                }, &color::Rgb(255,0,0)
-            ),
-            Figure::pyramid(
-                10f64, 
-                Vector {
-                    x : 49f64,
-                    y : -11f64,
-                    z : 20f64,
-                }, &color::Rgb(255,0,255)
-            ),
-            Figure::pyramid(
-                10f64, 
-                Vector {
-                    x : -70f64,
-                    y : -6f64,
-                    z : 20f64,
-                }, &color::Rgb(0,0,255)
-            ),
-            Figure::pyramid(
-                10f64, 
-                Vector {
-                    x : 20f64,
-                    y : 0f64,
-                    z : 20f64,
-                }, &color::Rgb(0,150,0)
-            ),
-            Figure::cube (
-                25f64, 
-                Vector {
-                    x : 0f64,
-                    y : 0f64,
-                    z : 60f64,
-                }, &color::Rgb(0,255,255)
-            ),
-        ];
-        let cant_figs: usize = figs.len();
-
-        // cube to the mode "cube"
-        let mut cube: tools::Figure = Figure::cube (30f64, Vector {x : 0f64, y : 0f64, z : 60f64}, &color::White);
-
-        // pyramid to the mode "pyramid"
-        let mut pyramid: tools::Figure = Figure::pyramid (20f64, Vector {x : 0f64, y : 0f64, z : 60f64}, &color::White);
-        
-        // ruby to the mode "ruby"
-        let mut ruby: tools::Figure = Figure::ruby (30f64, Vector {x : 0f64, y : 0f64, z : 60f64}, &color::White);
-
-        let mut mode: usize = 0;
-        while mode < CANT_OPT + 1 {
-            match mode {
-                0 => {
-                    // write selection square. 
-                    let h = win.get_height() as f64;
-                    let dim: f64 = (h / 2f64) - h * (INIT_POS_OPT + SPACE_POS_OPT * (read_lock(&opti) as f64));
-                    let mut fig = Figure::square (
-                        3, 
-                        20, 
-                        Vector {
-                            x: 0f64,
-                            y: -dim,
-                            z: 0f64,
-                        },
-                        &color::White
-                    );
-                    win.draw(&mut fig);
-                    
-                    // write options. 
-                    write_options(&mut win, OPTS);
-                },
-                1 => {
-                    // draw and rotate the figures
-                    figs[cant_figs - 1].rotate_in_y(0.2f64);
-                    figs[cant_figs - 1].rotate_in_x(0.07f64);
-
-                    figs[0].rotate_in_y(0.3f64);
-                    figs[1].rotate_in_y(0.3f64);
-                    figs[2].rotate_in_y(0.3f64);
-                    figs[3].rotate_in_y(-0.2f64);
-                    figs[3].rotate_in_x(0.03f64);
-                    figs[4].rotate_in_y(-0.4f64);
-                    figs[4].rotate_in_x(0.13f64);
-                    figs[5].rotate_in_y(-0.7f64);
-                    figs[5].rotate_in_x(0.11f64);
-
-                    draw_figures(&mut win, &mut figs);
-                    win.write("Libertad", 0.5f64, 0.5f64, Writemodes::Centered, &color::White);
-                },
-                2 => {
-                    cube.rotate_in_y(0.2f64);
-                    cube.rotate_in_x(0.07f64);
-                    win.draw(&mut cube);
-                },
-                3 =>  {
-                    pyramid.rotate_in_y(0.2f64);
-                    pyramid.rotate_in_x(0.07f64);
-                    win.draw(&mut pyramid);
-                },
-                4 =>  {
-                    ruby.rotate_in_y(0.2f64);
-                    ruby.rotate_in_x(0.07f64);
-                    win.draw(&mut ruby);
+        // the scene of the mode "things", its keyframes are counted from now.
+        let mut things: Scene = things;
+        let start = Instant::now();
+
+        // the camera of "things" looks to the center of the scene, so it can go around it and get
+        // nearer.
+        let things_camera = camera_to(things.get_center());
+
+        // the figures of the views with one figure.
+        let mut figures = Figures::get_new(model);
+
+        // the clock says how much time passed since the last frame, the animations move as much
+        // as that time says.
+        let mut clock = FrameClock::get_new(target_fps);
+
+        let mut app = App::get_new();
+        let mut last_state = State::Exit;
+        loop {
+            let delta: f64 = clock.tick();
+
+            // the events that arrived since the last frame change the state, and the commands are
+            // done with the figure of the view. If the keyboard thread ended without saying it (it
+            // panicked), the program can't be used anymore, so it ends.
+            loop {
+                let event = match receiver.try_recv() {
+                    Ok(event) => event,
+                    Err(TryRecvError::Empty) => break,
+                    Err(TryRecvError::Disconnected) => {
+                        app.handle(Event::Closed);
+                        break;
+                    },
+                };
+                if let (Some(command), State::View(view)) = (app.handle(event), app.get_state()) {
+                    apply(command, figures.get_mut(view), things_camera, &mut win);
+                }
+            }
+            let state = app.get_state();
+            if state == State::Exit {
+                return win;
+            }
+
+            // in the views with one figure the camera looks to the figure, so it can get nearer.
+            // The camera of the View is set again each time it is opened.
+            if state != last_state {
+                let fig = match state {
+                    State::View(view) => figures.get_mut(view),
+                    _ => None,
+                };
+                let camera = match (state, fig) {
+                    (_, Some(fig)) => camera_to(fig.get_position()),
+                    (State::View(View::Things), None) => things_camera,
+                    _ => Camera::default(),
+                };
+                win.set_camera(camera);
+                last_state = state;
+            }
+
+            // the menu is flat, the figures are seen with perspective. The models readed from
+            // files may not be closed, so all its edges are drawed, and the cube is filled.
+            match state {
+                State::Menu => win.set_projection(Projection::Orthographic),
+                _ => win.set_projection(Projection::perspective(FOCAL, NEAR)),
+            }
+            match state {
+                State::Menu | State::View(View::Model) => win.set_draw_mode(DrawMode::Wireframe),
+                State::View(View::Cube) => win.set_draw_mode(DrawMode::Solid),
+                _ => win.set_draw_mode(DrawMode::HiddenLine),
+            }
+
+            // the single figures are drawed with more resolution.
+            match state {
+                State::View(View::Pyramid) => win.set_raster_mode(RasterMode::HalfBlock),
+                State::View(View::Ruby) | State::View(View::Model) => win.set_raster_mode(RasterMode::Braille),
+                _ => win.set_raster_mode(RasterMode::Cell),
+            }
+
+            match state {
+                State::Menu => app.get_menu_mut().draw(&mut win),
+                State::View(View::Things) => {
+                    // the figures that fly behind the camera start again.
+                    things.advance(delta);
+                    for fig in things.get_figures_mut() {
+                        if fig.get_position().z < -10f64 {
+                            fig.reset_transform();
+                        }
+                    }
+                    things.animate(start.elapsed().as_secs_f64());
+
+                    win.draw_scene(&things);
+                    win.write("Libertad", 0.5f64, 0.5f64, Writemodes::Centered, COLOR_WHITE);
                 },
-                5 =>  {
+                State::View(view) => {
+                    match figures.get_mut(view) {
+                        Some(fig) => {
+                            if !app.is_paused() {
+                                fig.advance(delta);
+                            }
+                            win.draw(fig);
+                            let ph = 1f64 - 1f64 / win.get_height() as f64;
+                            win.write(HELP, ph, 0.5f64, Writemodes::Centered, COLOR_WHITE);
+                        },
+                        None => {
+                            win.write("No model, pass an obj file as argument", 0.5f64, 0.5f64, Writemodes::Centered, COLOR_WHITE);
+                        },
+                    }
                 },
-                _ => {},
+                State::Exit => {},
+            }
+
+            if show_stats {
+                win.write(&clock.stats(), 0f64, 0f64, Writemodes::ToLeft, COLOR_WHITE);
             }
 
             //print and clear.
             win.print();
-            wait(0.02f64);
             win.clear();
-
-            mode = read_lock(&modex);
         }
     });
     
-    let thread2 = thread::spawn(move || {
-        let mut exit: bool = false;
-        let mut keyboard = Keyboard::new();
-        while !exit {
-            if read_lock(&_modex) == 0 {
-                let data = read_lock(&_opti);
-                match keyboard.read_key() {
-                    Keys::Enter => {
-                        write_lock(&_modex, data + 1);
-                        if data == CANT_OPT { exit = true; }
-                    },
-                    Keys::Up => {
-                        let data: usize = data - if data > 0 { 1 } else { 0 };
-                        write_lock(&_opti, data);
-                    },
-                    Keys::Down => {
-                        let data: usize = data + if data < CANT_OPT { 1 } else { 0 };
-                        write_lock(&_opti, data);
-                    },
-                    _ => {}
-                }
-            } else {
-                match keyboard.read_key() {
-                    Keys::Enter => { write_lock(&_modex, 0); },
-                    _ => {}
-                }
-            }
-        }
-    });
+    // the keys are readed with termion, because the terminal is in raw mode.
+    let thread2 = input::spawn(sender, Arc::clone(&running));
 
-    thread1.join().unwrap();
-    thread2.join().unwrap();
-   
-    clearscreen::clear().expect("isn't possible to clear the screen.");
+    // when the render thread ends (or panics) the keyboard thread is stopped, and then the Window
+    // is dropped, so the terminal is restored after both threads ended.
+    let result = thread1.join();
+    running.store(false, Ordering::SeqCst);
+    let input = thread2.join();
+    match result {
+        Ok(win) => drop(win),
+        Err(err) => std::panic::resume_unwind(err),
+    }
+    if let Err(err) = input {
+        std::panic::resume_unwind(err);
+    }
 }
@@ -0,0 +1,357 @@
+/* Menu widget.
+ *
+ * A Menu is a list of items written with Window::write, the selected one in reverse video. It is
+ * only text, so it looks the same in every raster mode and it can be written over any drawing.
+ * An item gives its action when it is chosen (the type of the action is chosen by who makes the
+ * Menu), or opens a submenu, that is shown in place of its parent until it is closed. When the
+ * items don't fit in the Window only some of them are shown, and the list scrolls to keep the
+ * selected one visible. The selection goes around: up from the first item goes to the last one.
+ * */
+
+use termion::color::Rgb;
+
+use crate::tools::{Window, Writemodes};
+
+/*
+ *  the rows over the items (the title and the mark of the items hidden above), and the rows of
+ *  each item (the item and a blank row, where the mark of the items hidden below goes).
+ * */
+static TITLE_ROWS: usize = 2;
+static ITEM_ROWS: usize = 2;
+
+static COLOR: Rgb = Rgb(255, 255, 255);
+
+/*
+ *  structs definition.
+ * */
+#[derive(Debug, Clone)]
+enum ItemKind<A> {
+    Action(A),
+    Submenu(Menu<A>),
+}
+
+#[derive(Debug, Clone)]
+struct Item<A> {
+    _label: String,
+    _kind: ItemKind<A>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Menu<A> {
+    _title: String,
+    _items: Vec<Item<A>>,
+    _selected: usize,
+
+    // the first item shown, it changes when the selected one would be out of the Window.
+    _scroll: usize,
+
+    // if the submenu of the selected item is shown.
+    _open: bool,
+}
+
+/*
+ *  struct implementation.
+ * */
+impl<A: Copy> Menu<A> {
+    pub fn get_new(title: &str) -> Menu<A> {
+        Menu {
+            _title: title.to_string(),
+            _items: Vec::new(),
+            _selected: 0,
+            _scroll: 0,
+            _open: false,
+        }
+    }
+
+    /* an item that gives the action when it is chosen. */
+    pub fn add(&mut self, label: &str, action: A) {
+        self._items.push(Item { _label: label.to_string(), _kind: ItemKind::Action(action) });
+    }
+
+    /* an item that opens the submenu when it is chosen. */
+    pub fn add_submenu(&mut self, label: &str, submenu: Menu<A>) {
+        self._items.push(Item { _label: label.to_string(), _kind: ItemKind::Submenu(submenu) });
+    }
+
+    pub fn get_title(&self) -> &str {
+        &self._title
+    }
+
+    pub fn get_selected(&self) -> usize {
+        self._selected
+    }
+
+    pub fn len(&self) -> usize {
+        self._items.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self._items.is_empty()
+    }
+
+    /* the Menu that is shown, itself or the deepest submenu open. */
+    pub fn get_active(&self) -> &Menu<A> {
+        match self.open_submenu() {
+            Some(submenu) => submenu.get_active(),
+            None => self,
+        }
+    }
+
+    fn get_active_mut(&mut self) -> &mut Menu<A> {
+        if self.open_submenu().is_none() {
+            return self;
+        }
+        match self._items[self._selected]._kind {
+            ItemKind::Submenu(ref mut submenu) => submenu.get_active_mut(),
+            ItemKind::Action(_) => unreachable!(),
+        }
+    }
+
+    fn open_submenu(&self) -> Option<&Menu<A>> {
+        match self._items.get(self._selected) {
+            Some(Item { _kind: ItemKind::Submenu(submenu), .. }) if self._open => Some(submenu),
+            _ => None,
+        }
+    }
+
+    /* move the selection of the Menu that is shown, going around at the ends. */
+    pub fn up(&mut self) {
+        let menu = self.get_active_mut();
+        if !menu.is_empty() {
+            menu._selected = (menu._selected + menu.len() - 1) % menu.len();
+        }
+    }
+
+    pub fn down(&mut self) {
+        let menu = self.get_active_mut();
+        if !menu.is_empty() {
+            menu._selected = (menu._selected + 1) % menu.len();
+        }
+    }
+
+    /* the action of the selected item, or None if the item opens a submenu (that is opened). */
+    pub fn choose(&mut self) -> Option<A> {
+        let menu = self.get_active_mut();
+        match menu._items.get(menu._selected)?._kind {
+            ItemKind::Action(action) => Some(action),
+            ItemKind::Submenu(_) => {
+                menu._open = true;
+                None
+            },
+        }
+    }
+
+    /* open the submenu of the selected item, if it has one. */
+    pub fn open(&mut self) {
+        let menu = self.get_active_mut();
+        if let Some(Item { _kind: ItemKind::Submenu(_), .. }) = menu._items.get(menu._selected) {
+            menu._open = true;
+        }
+    }
+
+    /* close the deepest submenu open, false if there was none. */
+    pub fn back(&mut self) -> bool {
+        if self.open_submenu().is_none() {
+            return false;
+        }
+        if let ItemKind::Submenu(ref mut submenu) = self._items[self._selected]._kind {
+            if submenu.back() {
+                return true;
+            }
+        }
+        self._open = false;
+        true
+    }
+
+    /* write the Menu that is shown in the Window: the title in the first row and the items below
+     * it, centered. */
+    pub fn draw(&mut self, win: &mut Window) {
+        let menu = self.get_active_mut();
+        let height = win.get_height();
+        let visible = (height.saturating_sub(TITLE_ROWS) / ITEM_ROWS).max(1);
+        if menu._selected < menu._scroll {
+            menu._scroll = menu._selected;
+        } else if menu._selected >= menu._scroll + visible {
+            menu._scroll = menu._selected + 1 - visible;
+        }
+        // if the Window grew, there may be room for the items hidden above.
+        menu._scroll = menu._scroll.min(menu.len().saturating_sub(visible));
+
+        let row = |row: usize| (row as f64 + 0.5f64) / height as f64;
+        win.write(&menu._title, row(0), 0.5f64, Writemodes::Centered, COLOR);
+        if menu._scroll > 0 {
+            win.write("^", row(TITLE_ROWS - 1), 0.5f64, Writemodes::Centered, COLOR);
+        }
+
+        // all the items have the width of the longest one, so the highlight has always the same
+        // width.
+        let labels: Vec<String> = menu._items.iter().map(|item| match item._kind {
+            ItemKind::Action(_) => item._label.clone(),
+            ItemKind::Submenu(_) => format!("{} >", item._label),
+        }).collect();
+        let width = labels.iter().map(|label| label.len()).max().unwrap_or(0);
+        for (n, label) in labels.iter().enumerate().skip(menu._scroll).take(visible) {
+            let text = format!(" {:^width$} ", label, width = width);
+            let ph = row(TITLE_ROWS + (n - menu._scroll) * ITEM_ROWS);
+            if n == menu._selected {
+                win.write_inverted(&text, ph, 0.5f64, Writemodes::Centered, COLOR);
+            } else {
+                win.write(&text, ph, 0.5f64, Writemodes::Centered, COLOR);
+            }
+        }
+        if menu._scroll + visible < menu.len() {
+            let ph = row(TITLE_ROWS + visible * ITEM_ROWS - 1);
+            win.write("v", ph, 0.5f64, Writemodes::Centered, COLOR);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn numbers(count: usize) -> Menu<usize> {
+        let mut menu = Menu::get_new("Numbers");
+        for n in 0..count {
+            menu.add(&format!("item {}", n), n);
+        }
+        menu
+    }
+
+    /* the text of the row without the spaces around, and the same only of the inverted cells. */
+    fn row(win: &Window, row: usize) -> (String, String) {
+        let (text, inverted) = win.get_row(row);
+        let highlight: String = text.chars().zip(inverted).filter(|&(_, inv)| inv).map(|(c, _)| c).collect();
+        (text.trim().to_string(), highlight.trim().to_string())
+    }
+
+    #[test]
+    fn selection_goes_around() {
+        let mut menu = numbers(3);
+        menu.up();
+        assert_eq!(menu.get_selected(), 2);
+        menu.down();
+        assert_eq!(menu.get_selected(), 0);
+        menu.down();
+        menu.down();
+        assert_eq!(menu.choose(), Some(2));
+
+        // an empty Menu does nothing.
+        let mut empty: Menu<usize> = Menu::get_new("Empty");
+        empty.up();
+        empty.down();
+        assert_eq!(empty.choose(), None);
+    }
+
+    #[test]
+    fn submenus_open_and_close() {
+        let mut menu = numbers(2);
+        let mut sub = numbers(3);
+        sub.add_submenu("deeper", numbers(1));
+        menu.add_submenu("more", sub);
+
+        // Right on an action does nothing.
+        menu.open();
+        assert_eq!(menu.get_active().get_title(), "Numbers");
+        assert!(!menu.back());
+
+        menu.up();
+        assert_eq!(menu.choose(), None);
+        assert_eq!(menu.get_active().len(), 4);
+        // the submenu moves, its parent keeps the selection.
+        menu.down();
+        assert_eq!(menu.get_active().get_selected(), 1);
+        assert_eq!(menu.get_selected(), 2);
+        assert_eq!(menu.choose(), Some(1));
+
+        menu.up();
+        menu.up();
+        menu.open();
+        assert_eq!(menu.get_active().len(), 1);
+        assert_eq!(menu.choose(), Some(0));
+
+        // back closes the deepest one first.
+        assert!(menu.back());
+        assert_eq!(menu.get_active().len(), 4);
+        assert!(menu.back());
+        assert_eq!(menu.get_active().len(), 3);
+        assert!(!menu.back());
+    }
+
+    #[test]
+    fn draw_highlights_the_selected_item() {
+        let mut menu = numbers(2);
+        menu.add_submenu("more", numbers(1));
+        menu.down();
+        let mut win = Window::get_new(10, 30);
+        menu.draw(&mut win);
+        assert_eq!(row(&win, 0).0, "Numbers");
+        assert_eq!(row(&win, 2), ("item 0".to_string(), "".to_string()));
+        assert_eq!(row(&win, 4), ("item 1".to_string(), "item 1".to_string()));
+        assert_eq!(row(&win, 6).0, "more >");
+        // nothing is hidden, so there are no marks.
+        assert_eq!(row(&win, 1).0, "");
+        assert_eq!(row(&win, 7).0, "");
+
+        // an open submenu is drawed in place of its parent.
+        menu.down();
+        menu.choose();
+        win.clear();
+        menu.draw(&mut win);
+        assert_eq!(row(&win, 0).0, "Numbers");
+        assert_eq!(row(&win, 2), ("item 0".to_string(), "item 0".to_string()));
+        assert_eq!(row(&win, 4).0, "");
+    }
+
+    #[test]
+    fn short_window_scrolls() {
+        // 7 rows: the title, the mark above, and two items with the mark below.
+        let mut menu = numbers(5);
+        let mut win = Window::get_new(7, 30);
+        menu.draw(&mut win);
+        assert_eq!(row(&win, 1).0, "");
+        assert_eq!(row(&win, 2).1, "item 0");
+        assert_eq!(row(&win, 4).0, "item 1");
+        assert_eq!(row(&win, 5).0, "v");
+
+        for _ in 0..3 {
+            menu.down();
+        }
+        win.clear();
+        menu.draw(&mut win);
+        assert_eq!(row(&win, 1).0, "^");
+        assert_eq!(row(&win, 2).0, "item 2");
+        assert_eq!(row(&win, 4).1, "item 3");
+        assert_eq!(row(&win, 5).0, "v");
+
+        // going up from the first item shows the last ones.
+        for _ in 0..4 {
+            menu.up();
+        }
+        assert_eq!(menu.get_selected(), 4);
+        win.clear();
+        menu.draw(&mut win);
+        assert_eq!(row(&win, 1).0, "^");
+        assert_eq!(row(&win, 2).0, "item 3");
+        assert_eq!(row(&win, 4).1, "item 4");
+        assert_eq!(row(&win, 5).0, "");
+
+        // when the Window grows, the hidden items above are shown again.
+        let mut win = Window::get_new(12, 30);
+        menu.draw(&mut win);
+        assert_eq!(row(&win, 1).0, "");
+        assert_eq!(row(&win, 2).0, "item 0");
+        assert_eq!(row(&win, 10).1, "item 4");
+    }
+
+    #[test]
+    fn tiny_window() {
+        let mut menu = numbers(3);
+        menu.down();
+        for height in 0..4 {
+            let mut win = Window::get_new(height, 10);
+            menu.draw(&mut win);
+        }
+        assert_eq!(menu.get_selected(), 1);
+    }
+}
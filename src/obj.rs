@@ -0,0 +1,273 @@
+/* Loader of Wavefront OBJ files.
+ *
+ * Only the geometry is readed: the vertices ("v"), the lines ("l") and the faces ("f"), the faces
+ * are converted to edges too. The other statements (normals, textures, groups, materials...) are
+ * ignored. The model is centered and scaled so its biggest side is as long as the dimention given,
+ * like the built-in Figures.
+ * */
+
+use std::collections::HashSet;
+use std::fmt;
+use termion::color::Rgb;
+
+use crate::tools::{Figure, Vector};
+
+/*
+ *  the geometry readed from the file, ready to be converted to a Figure.
+ * */
+#[derive(Debug, Clone)]
+pub struct ObjModel {
+    _vertices: Vec<Vector>,
+    _edges: Vec<(usize, usize)>,
+    _faces: Vec<Vec<usize>>,
+}
+
+/*
+ *  error definition. The lines are counted from 1, like in the editors.
+ * */
+#[derive(Debug)]
+pub enum ObjError {
+    Io(std::io::Error),
+    Syntax { line: usize, message: String },
+    BadIndex { line: usize, index: i64 },
+    Empty,
+}
+
+impl fmt::Display for ObjError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ObjError::Io(err) => write!(f, "can't read the obj file: {}", err),
+            ObjError::Syntax { line, message } => write!(f, "line {}: {}", line, message),
+            ObjError::BadIndex { line, index } => write!(f, "line {}: there is no vertex {}", line, index),
+            ObjError::Empty => write!(f, "the obj file has no vertices"),
+        }
+    }
+}
+
+impl std::error::Error for ObjError {}
+
+impl From<std::io::Error> for ObjError {
+    fn from(err: std::io::Error) -> ObjError {
+        ObjError::Io(err)
+    }
+}
+
+impl ObjModel {
+    pub fn into_figure(self, position: Vector, color: Rgb) -> Figure {
+        Figure::mesh(self._vertices, self._edges, self._faces, position, color)
+    }
+}
+
+/*
+ *  functions.
+ * */
+
+pub fn load(path: &str, dimention: f64) -> Result<ObjModel, ObjError> {
+    let source = std::fs::read_to_string(path)?;
+    parse(&source, dimention)
+}
+
+pub fn parse(source: &str, dimention: f64) -> Result<ObjModel, ObjError> {
+    let mut vertices: Vec<Vector> = Vec::new();
+    let mut edges: Vec<(usize, usize)> = Vec::new();
+    let mut faces: Vec<Vec<usize>> = Vec::new();
+    let mut seen: HashSet<(usize, usize)> = HashSet::new();
+
+    for (number, text) in source.lines().enumerate() {
+        let line = number + 1;
+        let text = match text.find('#') {
+            Some(pos) => &text[..pos],
+            None => text,
+        };
+        let mut tokens = text.split_whitespace();
+        match tokens.next() {
+            Some("v") => {
+                let mut coords = [0f64; 3];
+                for coord in &mut coords {
+                    *coord = parse_float(tokens.next(), line)?;
+                }
+                vertices.push(Vector {x: coords[0], y: coords[1], z: coords[2]});
+            },
+            Some("l") => {
+                let indexes = parse_indexes(tokens, vertices.len(), line)?;
+                if indexes.len() < 2 {
+                    return Err(syntax(line, "a line needs at least two vertices"));
+                }
+                for pair in indexes.windows(2) {
+                    push_edge(&mut edges, &mut seen, pair[0], pair[1]);
+                }
+            },
+            Some("f") => {
+                let indexes = parse_indexes(tokens, vertices.len(), line)?;
+                if indexes.len() < 3 {
+                    return Err(syntax(line, "a face needs at least three vertices"));
+                }
+                for i in 0..indexes.len() {
+                    push_edge(&mut edges, &mut seen, indexes[i], indexes[(i + 1) % indexes.len()]);
+                }
+                faces.push(indexes);
+            },
+            _ => {},
+        }
+    }
+
+    if vertices.is_empty() {
+        return Err(ObjError::Empty);
+    }
+    normalize(&mut vertices, dimention);
+
+    Ok(ObjModel {
+        _vertices: vertices,
+        _edges: edges,
+        _faces: faces,
+    })
+}
+
+fn syntax(line: usize, message: &str) -> ObjError {
+    ObjError::Syntax { line, message: message.to_string() }
+}
+
+fn parse_float(token: Option<&str>, line: usize) -> Result<f64, ObjError> {
+    let token = token.ok_or_else(|| syntax(line, "a vertex needs three coordinates"))?;
+    match token.parse::<f64>() {
+        // "nan" and "inf" are readed as numbers, but they can't be centered nor scaled.
+        Ok(value) if value.is_finite() => Ok(value),
+        _ => Err(syntax(line, &format!("\"{}\" is not a number", token))),
+    }
+}
+
+/* the indexes start at 1, and the negative ones count from the last vertex readed. The texture
+ * and normal indexes ("1/2/3") are discarded. */
+fn parse_indexes<'a>(tokens: impl Iterator<Item = &'a str>, count: usize, line: usize) -> Result<Vec<usize>, ObjError> {
+    let mut indexes: Vec<usize> = Vec::new();
+    for token in tokens {
+        let first = token.split('/').next().unwrap_or(token);
+        let index: i64 = first
+            .parse()
+            .map_err(|_| syntax(line, &format!("\"{}\" is not an index", token)))?;
+        let real = if index < 0 { count as i64 + index } else { index - 1 };
+        if index == 0 || real < 0 || real >= count as i64 {
+            return Err(ObjError::BadIndex { line, index });
+        }
+        indexes.push(real as usize);
+    }
+    Ok(indexes)
+}
+
+fn push_edge(edges: &mut Vec<(usize, usize)>, seen: &mut HashSet<(usize, usize)>, a: usize, b: usize) {
+    let key = (a.min(b), a.max(b));
+    if a != b && seen.insert(key) {
+        edges.push((a, b));
+    }
+}
+
+/* move the center of the model to the origin and scale it to the dimention. The y axis of the obj
+ * files points to the top, and in the scream points to the bottom, so it is inverted. */
+fn normalize(vertices: &mut [Vector], dimention: f64) {
+    let mut min = vertices[0];
+    let mut max = vertices[0];
+    for vect in vertices.iter() {
+        min = Vector {x: min.x.min(vect.x), y: min.y.min(vect.y), z: min.z.min(vect.z)};
+        max = Vector {x: max.x.max(vect.x), y: max.y.max(vect.y), z: max.z.max(vect.z)};
+    }
+    let center = (min + max) * 0.5f64;
+    let side = (max.x - min.x).max(max.y - min.y).max(max.z - min.z);
+    let scale = if side > 0f64 { dimention / side } else { 1f64 };
+    for vect in vertices.iter_mut() {
+        let moved = (*vect - center) * scale;
+        *vect = Vector {x: moved.x, y: -moved.y, z: moved.z};
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    static SQUARE: &str = "v 0 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 0\n";
+
+    /* the extremes of the vertices of the model, for each axis. */
+    fn bounds(model: &ObjModel) -> (Vector, Vector) {
+        let mut min = model._vertices[0];
+        let mut max = model._vertices[0];
+        for v in model._vertices.iter() {
+            min = Vector {x: min.x.min(v.x), y: min.y.min(v.y), z: min.z.min(v.z)};
+            max = Vector {x: max.x.max(v.x), y: max.y.max(v.y), z: max.z.max(v.z)};
+        }
+        (min, max)
+    }
+
+    #[test]
+    fn faces_and_lines_become_edges_once() {
+        let source = format!("{}f 1 2 3 4\nl 1 2 3\nl 3 1\n", SQUARE);
+        let model = parse(&source, 10f64).unwrap();
+        assert_eq!(model._faces, vec![vec![0, 1, 2, 3]]);
+        // the lines 1-2 and 2-3 are sides of the face, only 3-1 is new.
+        assert_eq!(model._edges, vec![(0, 1), (1, 2), (2, 3), (3, 0), (2, 0)]);
+    }
+
+    #[test]
+    fn negative_and_slashed_indexes() {
+        let source = format!("{}f -4/1/1 -3/2/2 -2//3\nl 4/7 -1\n", SQUARE);
+        let model = parse(&source, 10f64).unwrap();
+        assert_eq!(model._faces, vec![vec![0, 1, 2]]);
+        // "l 4 -1" is the same vertex twice, so it gives no edge.
+        assert_eq!(model._edges, vec![(0, 1), (1, 2), (2, 0)]);
+    }
+
+    #[test]
+    fn bad_index_says_the_line() {
+        let source = format!("# a square\n{}f 1 2 5\n", SQUARE);
+        match parse(&source, 10f64) {
+            Err(ObjError::BadIndex { line: 6, index: 5 }) => {},
+            other => panic!("unexpected result: {:?}", other),
+        }
+        match parse("v 0 0 0\nl 1 0\n", 10f64) {
+            Err(ObjError::BadIndex { line: 2, index: 0 }) => {},
+            other => panic!("unexpected result: {:?}", other),
+        }
+        match parse("v 0 0 0\nl 1 -2\n", 10f64) {
+            Err(ObjError::BadIndex { line: 2, index: -2 }) => {},
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn syntax_errors_say_the_line() {
+        for (source, expected) in [
+            ("v 0 0 0\nv 1 x 0\n", 2),
+            ("v 0 0\n", 1),
+            ("v 0 0 0\nv 1 0 0\n\nl 1\n", 4),
+            ("v 0 0 0\nv 1 0 0\nf 1 2\n", 3),
+            ("v 0 0 0\nf 1 a 1\n", 2),
+            ("v 0 0 0\nv nan 0 0\n", 2),
+            ("v 0 0 0\nv 1 0 0\nv 0 -inf 0\n", 3),
+        ] {
+            match parse(source, 10f64) {
+                Err(ObjError::Syntax { line, .. }) => assert_eq!(line, expected, "{:?}", source),
+                other => panic!("unexpected result for {:?}: {:?}", source, other),
+            }
+        }
+    }
+
+    #[test]
+    fn no_vertices_is_empty() {
+        assert!(matches!(parse("", 10f64), Err(ObjError::Empty)));
+        assert!(matches!(parse("# only a comment\nvn 0 0 1\n", 10f64), Err(ObjError::Empty)));
+    }
+
+    #[test]
+    fn the_model_is_centered_and_scaled() {
+        let source = "v 10 20 30\nv 14 21 31\nv 12 22 32\n";
+        let model = parse(source, 8f64).unwrap();
+        let (min, max) = bounds(&model);
+        // the biggest side (x, 4 long) is as long as the dimention, and the center is the origin.
+        assert!((max.x - min.x - 8f64).abs() < 1e-9);
+        assert!((max.y - min.y - 4f64).abs() < 1e-9);
+        assert!((max.z - min.z - 4f64).abs() < 1e-9);
+        for (a, b) in [(min.x, max.x), (min.y, max.y), (min.z, max.z)] {
+            assert!((a + b).abs() < 1e-9);
+        }
+        // the y axis is inverted: the first vertex is the lowest in the file, so the highest here.
+        assert!((model._vertices[0].y - max.y).abs() < 1e-9);
+    }
+}
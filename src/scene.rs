@@ -0,0 +1,187 @@
+/* Groups of Figures that move together.
+ *
+ * Each node of the Scene has a name, a Figure and maybe a parent. The transform of a Figure that
+ * has a parent is relative to its parent, so when the parent moves, rotates or is scaled, its
+ * children do the same (a small pyramid can orbit a cube that rotates). The nodes without
+ * vertices (pivots) are used to move other nodes without drawing anything.
+ * */
+
+use termion::color::Rgb;
+
+use crate::timeline::Timeline;
+use crate::tools::{Figure, Matrix4, Vector};
+
+/*
+ *  structs definition.
+ * */
+#[derive(Debug)]
+pub struct Node {
+    _name: String,
+    _figure: Figure,
+    _parent: Option<usize>,
+}
+
+/*
+ *  the nodes are kept in the order they were added, so a parent is always before its children.
+ *  The Timeline animates the Figures by their names.
+ * */
+#[derive(Debug, Default)]
+pub struct Scene {
+    _nodes: Vec<Node>,
+    _timeline: Timeline,
+}
+
+/*
+ *  struct implementation.
+ * */
+impl Node {
+    pub fn get_name(&self) -> &str {
+        &self._name
+    }
+
+    pub fn get_figure(&self) -> &Figure {
+        &self._figure
+    }
+
+    pub fn get_parent(&self) -> Option<usize> {
+        self._parent
+    }
+}
+
+impl Scene {
+    pub fn get_new() -> Scene {
+        Scene { _nodes: Vec::new(), _timeline: Timeline::get_new() }
+    }
+
+    /* add a node without parent, the result is the index of the node. */
+    pub fn add(&mut self, name: &str, fig: Figure) -> usize {
+        self.push(name, fig, None)
+    }
+
+    /* add a node whose transform is relative to the node of the index given. */
+    pub fn add_child(&mut self, parent: usize, name: &str, fig: Figure) -> usize {
+        self.push(name, fig, Some(parent))
+    }
+
+    /* add a node that is not drawed, only to move its children. */
+    pub fn add_pivot(&mut self, parent: Option<usize>, name: &str, position: Vector) -> usize {
+        let fig = Figure::mesh(Vec::new(), Vec::new(), Vec::new(), position, Rgb(255, 255, 255));
+        self.push(name, fig, parent)
+    }
+
+    fn push(&mut self, name: &str, fig: Figure, parent: Option<usize>) -> usize {
+        if let Some(i) = parent {
+            assert!(i < self._nodes.len(), "there is no node {} in the scene", i);
+        }
+        self._nodes.push(Node {
+            _name: name.to_string(),
+            _figure: fig,
+            _parent: parent,
+        });
+        self._nodes.len() - 1
+    }
+
+    pub fn get_nodes(&self) -> &[Node] {
+        &self._nodes
+    }
+
+    /* index of the first node with the name. */
+    pub fn find(&self, name: &str) -> Option<usize> {
+        self._nodes.iter().position(|node| node._name == name)
+    }
+
+    pub fn get_figure(&self, name: &str) -> Option<&Figure> {
+        self.find(name).map(|i| &self._nodes[i]._figure)
+    }
+
+    pub fn get_figure_mut(&mut self, name: &str) -> Option<&mut Figure> {
+        self.find(name).map(move |i| &mut self._nodes[i]._figure)
+    }
+
+    pub fn get_figures_mut(&mut self) -> impl Iterator<Item = &mut Figure> {
+        self._nodes.iter_mut().map(|node| &mut node._figure)
+    }
+
+    /* move and turn all the Figures as much as their velocities and spins say for the time given
+     * (in seconds). */
+    pub fn advance(&mut self, seconds: f64) {
+        for node in self._nodes.iter_mut() {
+            node._figure.advance(seconds);
+        }
+    }
+
+    pub fn get_timeline(&self) -> &Timeline {
+        &self._timeline
+    }
+
+    pub fn set_timeline(&mut self, timeline: Timeline) {
+        self._timeline = timeline;
+    }
+
+    /* put in the Figures the values of the Timeline at the time given (in seconds since the
+     * animation started). The animated properties replace the ones changed by advance. */
+    pub fn animate(&mut self, time: f64) {
+        let time = self._timeline.local_time(time);
+        for anim in self._timeline.get_animations() {
+            if let Some(i) = self._nodes.iter().position(|node| node._name == anim.get_target()) {
+                anim.apply(&mut self._nodes[i]._figure, time);
+            }
+        }
+    }
+
+    /* the middle of the box around the positions of the nodes, in the coordinates of the scene
+     * (the origin if the scene is empty). */
+    pub fn get_center(&self) -> Vector {
+        let positions: Vec<Vector> = self._nodes.iter().zip(self.parent_matrices())
+            .map(|(node, parent)| parent.transform_point(node._figure.get_position()))
+            .collect();
+        let Some(&first) = positions.first() else {
+            return Vector {x: 0f64, y: 0f64, z: 0f64};
+        };
+        let (mut min, mut max) = (first, first);
+        for v in positions.iter() {
+            min = Vector {x: min.x.min(v.x), y: min.y.min(v.y), z: min.z.min(v.z)};
+            max = Vector {x: max.x.max(v.x), y: max.y.max(v.y), z: max.z.max(v.z)};
+        }
+        (min + max) * 0.5f64
+    }
+
+    /* the matrix that takes the coordinates of the parent of each node to the coordinates of the
+     * scene (the identity for the nodes without parent). */
+    pub fn parent_matrices(&self) -> Vec<Matrix4> {
+        let mut worlds: Vec<Matrix4> = Vec::with_capacity(self._nodes.len());
+        let mut parents: Vec<Matrix4> = Vec::with_capacity(self._nodes.len());
+        for node in self._nodes.iter() {
+            let parent = match node._parent {
+                Some(i) => worlds[i],
+                None => Matrix4::identity(),
+            };
+            worlds.push(parent * node._figure.get_transform().matrix());
+            parents.push(parent);
+        }
+        parents
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn center_of_the_positions_in_the_scene() {
+        let mut scene = Scene::get_new();
+        assert_eq!(scene.get_center().length(), 0f64);
+
+        let parent = scene.add_pivot(None, "parent", Vector {x: 0f64, y: 0f64, z: 60f64});
+        // the child is relative to its parent, so it is at (40, 0, 60) in the scene.
+        scene.add_pivot(Some(parent), "child", Vector {x: 40f64, y: 0f64, z: 0f64});
+        scene.add_pivot(None, "low", Vector {x: 0f64, y: -20f64, z: 40f64});
+        let center = scene.get_center();
+        assert!((center - Vector {x: 20f64, y: -10f64, z: 50f64}).length() < 1e-9);
+
+        // moving the parent moves the child too.
+        scene.get_figure_mut("parent").unwrap().set_position(Vector {x: 0f64, y: 0f64, z: 0f64});
+        assert!((scene.get_center() - Vector {x: 20f64, y: -10f64, z: 20f64}).length() < 1e-9);
+        assert!(scene.get_figure_mut("nobody").is_none());
+    }
+}
@@ -0,0 +1,392 @@
+/* Loader of the scene files.
+ *
+ * A scene file is a TOML file with a list of figures ([[figure]]). Each figure has a shape (one of
+ * the built-in Figures, a mesh readed from an obj file, or a pivot that is not drawed), a size, a
+ * position, a color, the angles that it turns in one second ("spin"), a velocity, a draw mode and
+ * the name of its parent (that must be before it in the file). The keyframes ([[keyframe]]) say
+ * the position, the rotation (angles around x, y and z), the scale or the color of a figure at a
+ * time (in seconds), and with "loop = true" the animation starts again after the last keyframe.
+ * See scenes/things.toml.
+ * */
+
+use std::fmt;
+use std::path::Path;
+use serde::Deserialize;
+use termion::color::Rgb;
+
+use crate::obj;
+use crate::scene::Scene;
+use crate::timeline::{Easing, Timeline};
+use crate::tools::{DrawMode, Figure, Quaternion, Vector};
+
+static SHAPES: &[&str] = &["cube", "ruby", "pyramid", "mesh", "pivot"];
+
+/*
+ *  what is readed from the file, before checking it.
+ * */
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SceneDef {
+    #[serde(default)]
+    figure: Vec<FigureDef>,
+    #[serde(default)]
+    keyframe: Vec<KeyframeDef>,
+    #[serde(default, rename = "loop")]
+    looping: bool,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct FigureDef {
+    name: Option<String>,
+    shape: String,
+    size: Option<f64>,
+    mesh: Option<String>,
+    #[serde(default)]
+    position: [f64; 3],
+    #[serde(default = "white")]
+    color: [u8; 3],
+    #[serde(default)]
+    spin: [f64; 3],
+    #[serde(default)]
+    velocity: [f64; 3],
+    draw_mode: Option<String>,
+    parent: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct KeyframeDef {
+    figure: String,
+    time: f64,
+    easing: Option<String>,
+    position: Option<[f64; 3]>,
+    rotation: Option<[f64; 3]>,
+    scale: Option<[f64; 3]>,
+    color: Option<[u8; 3]>,
+}
+
+/*
+ *  error definition. The figures and the keyframes are counted from 1, in the order of the file.
+ * */
+#[derive(Debug)]
+pub enum SceneError {
+    Io(std::io::Error),
+    Syntax(toml::de::Error),
+    Figure { index: usize, name: String, message: String },
+    Mesh { index: usize, name: String, error: obj::ObjError },
+    Keyframe { index: usize, figure: String, message: String },
+}
+
+impl fmt::Display for SceneError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SceneError::Io(err) => write!(f, "can't read the scene file: {}", err),
+            SceneError::Syntax(err) => write!(f, "{}", err),
+            SceneError::Figure { index, name, message } => write!(f, "figure {} (\"{}\"): {}", index, name, message),
+            SceneError::Mesh { index, name, error } => write!(f, "figure {} (\"{}\"): {}", index, name, error),
+            SceneError::Keyframe { index, figure, message } => write!(f, "keyframe {} (of \"{}\"): {}", index, figure, message),
+        }
+    }
+}
+
+impl std::error::Error for SceneError {}
+
+impl From<std::io::Error> for SceneError {
+    fn from(err: std::io::Error) -> SceneError {
+        SceneError::Io(err)
+    }
+}
+
+/*
+ *  functions.
+ * */
+
+pub fn load(path: &str) -> Result<Scene, SceneError> {
+    let source = std::fs::read_to_string(path)?;
+    let dir = Path::new(path).parent().unwrap_or_else(|| Path::new("."));
+    parse(&source, dir)
+}
+
+/* the paths of the meshes are relative to the directory given (the one of the scene file). */
+pub fn parse(source: &str, dir: &Path) -> Result<Scene, SceneError> {
+    let def: SceneDef = toml::from_str(source).map_err(SceneError::Syntax)?;
+    let mut scene = Scene::get_new();
+    for (number, fig) in def.figure.into_iter().enumerate() {
+        let index = number + 1;
+        let name = fig.name.clone().unwrap_or_else(|| format!("figure {}", index));
+        let error = |message: String| SceneError::Figure { index, name: name.clone(), message };
+
+        if scene.find(&name).is_some() {
+            return Err(error("there is another figure with this name".to_string()));
+        }
+        let parent = match fig.parent {
+            Some(ref parent) => match scene.find(parent) {
+                Some(i) => Some(i),
+                None => return Err(error(format!("the parent \"{}\" is not defined before", parent))),
+            },
+            None => None,
+        };
+
+        if !SHAPES.contains(&fig.shape.as_str()) {
+            return Err(error(format!(
+                "unknown shape \"{}\" (it can be {})", fig.shape, SHAPES.join(", ")
+            )));
+        }
+
+        let position = vector(fig.position);
+        let color = Rgb(fig.color[0], fig.color[1], fig.color[2]);
+        let size = match (fig.shape.as_str(), fig.size) {
+            ("pivot", _) => 0f64,
+            (_, Some(size)) if size > 0f64 => size,
+            (_, Some(_)) => return Err(error("the size must be positive".to_string())),
+            (_, None) => return Err(error(format!("a {} needs a size", fig.shape))),
+        };
+        if fig.mesh.is_some() && fig.shape != "mesh" {
+            return Err(error(format!("a {} can't have a mesh file", fig.shape)));
+        }
+        let mut figure = match fig.shape.as_str() {
+            "cube" => Figure::cube(size, position, color),
+            "ruby" => Figure::ruby(size, position, color),
+            "pyramid" => Figure::pyramid(size, position, color),
+            "pivot" => Figure::mesh(Vec::new(), Vec::new(), Vec::new(), position, color),
+            "mesh" => {
+                let file = match fig.mesh {
+                    Some(ref file) => dir.join(file),
+                    None => return Err(error("a mesh needs the path of its obj file".to_string())),
+                };
+                match obj::load(&file.to_string_lossy(), size) {
+                    Ok(model) => model.into_figure(position, color),
+                    Err(err) => return Err(SceneError::Mesh { index, name: name.clone(), error: err }),
+                }
+            },
+            _ => unreachable!(),
+        };
+
+        let mode = match fig.draw_mode.as_deref() {
+            None => None,
+            Some("wireframe") => Some(DrawMode::Wireframe),
+            Some("hidden_line") => Some(DrawMode::HiddenLine),
+            Some("solid") => Some(DrawMode::Solid),
+            Some(other) => return Err(error(format!(
+                "unknown draw mode \"{}\" (it can be wireframe, hidden_line or solid)", other
+            ))),
+        };
+        figure.set_draw_mode(mode);
+        figure.set_spin(vector(fig.spin));
+        figure.set_velocity(vector(fig.velocity));
+
+        match parent {
+            Some(i) => scene.add_child(i, &name, figure),
+            None => scene.add(&name, figure),
+        };
+    }
+
+    let mut timeline = Timeline::get_new();
+    timeline.set_looping(def.looping);
+    for (number, key) in def.keyframe.into_iter().enumerate() {
+        let index = number + 1;
+        let error = |message: &str| SceneError::Keyframe { index, figure: key.figure.clone(), message: message.to_string() };
+
+        if scene.find(&key.figure).is_none() {
+            return Err(error("there is no figure with this name"));
+        }
+        if key.time < 0f64 || !key.time.is_finite() {
+            return Err(error("the time must be a number not negative"));
+        }
+        let easing = match key.easing.as_deref() {
+            None | Some("linear") => Easing::Linear,
+            Some("ease_in") => Easing::EaseIn,
+            Some("ease_out") => Easing::EaseOut,
+            Some("ease_in_out") => Easing::EaseInOut,
+            Some("step") => Easing::Step,
+            Some(_) => return Err(error("unknown easing (it can be linear, ease_in, ease_out, ease_in_out or step)")),
+        };
+        if key.position.is_none() && key.rotation.is_none() && key.scale.is_none() && key.color.is_none() {
+            return Err(error("a keyframe needs a position, a rotation, a scale or a color"));
+        }
+
+        if let Some(position) = key.position {
+            timeline.add_position(&key.figure, key.time, vector(position), easing);
+        }
+        if let Some([x, y, z]) = key.rotation {
+            timeline.add_rotation(&key.figure, key.time, Quaternion::from_euler(x, y, z), easing);
+        }
+        if let Some(scale) = key.scale {
+            timeline.add_scale(&key.figure, key.time, vector(scale), easing);
+        }
+        if let Some([r, g, b]) = key.color {
+            timeline.add_color(&key.figure, key.time, Rgb(r, g, b), easing);
+        }
+    }
+    scene.set_timeline(timeline);
+    Ok(scene)
+}
+
+fn vector(coords: [f64; 3]) -> Vector {
+    Vector {x: coords[0], y: coords[1], z: coords[2]}
+}
+
+fn white() -> [u8; 3] {
+    [255, 255, 255]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_str(source: &str) -> Result<Scene, SceneError> {
+        parse(source, Path::new("scenes"))
+    }
+
+    /* the index, the name and the message of the error of a figure. */
+    fn figure_error(source: &str) -> (usize, String, String) {
+        match parse_str(source) {
+            Err(SceneError::Figure { index, name, message }) => (index, name, message),
+            Err(other) => panic!("unexpected error: {}", other),
+            Ok(_) => panic!("the scene was accepted"),
+        }
+    }
+
+    fn keyframe_error(source: &str) -> (usize, String, String) {
+        match parse_str(source) {
+            Err(SceneError::Keyframe { index, figure, message }) => (index, figure, message),
+            Err(other) => panic!("unexpected error: {}", other),
+            Ok(_) => panic!("the scene was accepted"),
+        }
+    }
+
+    #[test]
+    fn the_bundled_scene_parses() {
+        let scene = parse_str(include_str!("../scenes/things.toml")).unwrap();
+        assert!(scene.find("orbit").is_some());
+        assert!(!scene.get_timeline().is_empty());
+        assert!(scene.get_timeline().get_looping());
+    }
+
+    #[test]
+    fn a_good_scene() {
+        let scene = parse_str(r#"
+            [[figure]]
+            name = "center"
+            shape = "pivot"
+
+            [[figure]]
+            name = "box"
+            shape = "cube"
+            size = 10
+            parent = "center"
+            color = [255, 0, 0]
+            spin = [0, 1, 0]
+            velocity = [2, 0, 0]
+            draw_mode = "solid"
+
+            [[keyframe]]
+            figure = "box"
+            time = 1
+            easing = "step"
+            scale = [2, 2, 2]
+        "#).unwrap();
+        let parent = scene.find("center");
+        let node = &scene.get_nodes()[scene.find("box").unwrap()];
+        assert_eq!((node.get_name(), node.get_parent()), ("box", parent));
+        let fig = scene.get_figure("box").unwrap();
+        assert_eq!(fig.get_color(), Rgb(255, 0, 0));
+        assert_eq!(fig.get_draw_mode(), Some(DrawMode::Solid));
+        assert_eq!((fig.get_spin().y, fig.get_velocity().x), (1f64, 2f64));
+        assert_eq!(scene.get_figure("center").unwrap().get_draw_mode(), None);
+        assert_eq!(scene.get_timeline().get_duration(), 1f64);
+    }
+
+    #[test]
+    fn duplicated_name() {
+        let (index, name, message) = figure_error(r#"
+            [[figure]]
+            name = "a"
+            shape = "pivot"
+
+            [[figure]]
+            name = "a"
+            shape = "pivot"
+        "#);
+        assert_eq!((index, name.as_str()), (2, "a"));
+        assert!(message.contains("another figure"), "{}", message);
+    }
+
+    #[test]
+    fn parent_after_its_child() {
+        let (index, name, message) = figure_error(r#"
+            [[figure]]
+            name = "child"
+            shape = "pivot"
+            parent = "later"
+
+            [[figure]]
+            name = "later"
+            shape = "pivot"
+        "#);
+        assert_eq!((index, name.as_str()), (1, "child"));
+        assert!(message.contains("\"later\" is not defined before"), "{}", message);
+    }
+
+    #[test]
+    fn unknown_shape() {
+        let (index, name, message) = figure_error("[[figure]]\nshape = \"sphere\"\nsize = 3\n");
+        // without a name, the figure is named by its number.
+        assert_eq!((index, name.as_str()), (1, "figure 1"));
+        assert!(message.contains("unknown shape \"sphere\""), "{}", message);
+    }
+
+    #[test]
+    fn missing_or_negative_size() {
+        let (_, _, message) = figure_error("[[figure]]\nshape = \"cube\"\n");
+        assert!(message.contains("needs a size"), "{}", message);
+        let (_, _, message) = figure_error("[[figure]]\nshape = \"ruby\"\nsize = -4\n");
+        assert!(message.contains("must be positive"), "{}", message);
+        let (_, _, message) = figure_error("[[figure]]\nshape = \"pyramid\"\nsize = 0\n");
+        assert!(message.contains("must be positive"), "{}", message);
+    }
+
+    #[test]
+    fn bad_easing() {
+        let (index, figure, message) = keyframe_error(r#"
+            [[figure]]
+            name = "a"
+            shape = "pivot"
+
+            [[keyframe]]
+            figure = "a"
+            time = 0
+            position = [0, 0, 0]
+
+            [[keyframe]]
+            figure = "a"
+            time = 1
+            easing = "bounce"
+            position = [1, 0, 0]
+        "#);
+        assert_eq!((index, figure.as_str()), (2, "a"));
+        assert!(message.contains("unknown easing"), "{}", message);
+    }
+
+    #[test]
+    fn keyframe_of_unknown_figure() {
+        let (index, figure, message) = keyframe_error(r#"
+            [[figure]]
+            name = "a"
+            shape = "pivot"
+
+            [[keyframe]]
+            figure = "b"
+            time = 0
+            position = [0, 0, 0]
+        "#);
+        assert_eq!((index, figure.as_str()), (1, "b"));
+        assert!(message.contains("no figure"), "{}", message);
+    }
+
+    #[test]
+    fn unknown_field_is_a_syntax_error() {
+        assert!(matches!(parse_str("[[figure]]\nshape = \"pivot\"\ncolour = [1, 2, 3]\n"), Err(SceneError::Syntax(_))));
+    }
+}
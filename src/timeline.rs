@@ -0,0 +1,375 @@
+/* Keyframe animation of the Figures of a Scene.
+ *
+ * The Timeline has the keyframes of the position, the rotation, the scale and the color of the
+ * Figures (by name). Its value in each moment depends only on the time since the animation
+ * started, so it looks the same in every computer, no matter how many frames are drawed. Between
+ * two keyframes the values are interpolated with the easing of the second one, before the first
+ * keyframe the value is the first one and after the last keyframe it is the last one (or the
+ * animation starts again if the Timeline loops).
+ * */
+
+use termion::color::Rgb;
+
+use crate::tools::{Figure, Quaternion, Vector};
+
+/*
+ *  how the time goes from one keyframe to the next one. In Linear mode it goes at the same
+ *  speed, in EaseIn mode it starts slow, in EaseOut mode it ends slow, in EaseInOut mode it starts
+ *  and ends slow, and in Step mode the value jumps when the next keyframe is reached.
+ * */
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Easing {
+    Linear,
+    EaseIn,
+    EaseOut,
+    EaseInOut,
+    Step,
+}
+
+/*
+ *  the values that can be mixed (with t from 0, the first value, to 1, the second one).
+ * */
+pub trait Interpolate: Copy {
+    fn interpolate(self, other: Self, t: f64) -> Self;
+}
+
+/*
+ *  structs definition.
+ * */
+#[derive(Debug, Copy, Clone)]
+pub struct Keyframe<T> {
+    pub time: f64,
+    pub value: T,
+    pub easing: Easing,
+}
+
+/*
+ *  the keyframes of one property, ordered by time.
+ * */
+#[derive(Debug, Clone)]
+pub struct Track<T> {
+    _keys: Vec<Keyframe<T>>,
+}
+
+/*
+ *  the tracks of one Figure, the empty ones don't change the Figure.
+ * */
+#[derive(Debug, Clone)]
+pub struct Animation {
+    _target: String,
+    _position: Track<Vector>,
+    _rotation: Track<Quaternion>,
+    _scale: Track<Vector>,
+    _color: Track<Rgb>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Timeline {
+    _animations: Vec<Animation>,
+    _looping: bool,
+}
+
+/*
+ *  struct implementation.
+ * */
+impl Easing {
+    /* the fraction of the change made when the fraction t of the time has passed. */
+    pub fn apply(&self, t: f64) -> f64 {
+        let t = t.clamp(0f64, 1f64);
+        match self {
+            Easing::Linear => t,
+            Easing::EaseIn => t * t,
+            Easing::EaseOut => t * (2f64 - t),
+            Easing::EaseInOut => t * t * (3f64 - 2f64 * t),
+            Easing::Step => if t < 1f64 { 0f64 } else { 1f64 },
+        }
+    }
+}
+
+impl Interpolate for Vector {
+    fn interpolate(self, other: Vector, t: f64) -> Vector {
+        self + (other - self) * t
+    }
+}
+
+/* spherical interpolation, it turns at the same speed by the shortest way. */
+impl Interpolate for Quaternion {
+    fn interpolate(self, other: Quaternion, t: f64) -> Quaternion {
+        let mut dot = self.w*other.w + self.x*other.x + self.y*other.y + self.z*other.z;
+        let mut other = other;
+        if dot < 0f64 {
+            other = Quaternion {w: -other.w, x: -other.x, y: -other.y, z: -other.z};
+            dot = -dot;
+        }
+        let (a, b) = if dot > 0.9995f64 {
+            // they are almost the same, so the linear interpolation is good enough.
+            (1f64 - t, t)
+        } else {
+            let angle = dot.acos();
+            let sin = angle.sin();
+            (((1f64 - t) * angle).sin() / sin, (t * angle).sin() / sin)
+        };
+        Quaternion {
+            w : self.w*a + other.w*b,
+            x : self.x*a + other.x*b,
+            y : self.y*a + other.y*b,
+            z : self.z*a + other.z*b,
+        }.normalize()
+    }
+}
+
+impl Interpolate for Rgb {
+    fn interpolate(self, other: Rgb, t: f64) -> Rgb {
+        let mix = |a: u8, b: u8| (a as f64 + (b as f64 - a as f64) * t).round() as u8;
+        Rgb(mix(self.0, other.0), mix(self.1, other.1), mix(self.2, other.2))
+    }
+}
+
+impl<T: Interpolate> Track<T> {
+    pub fn get_new() -> Track<T> {
+        Track { _keys: Vec::new() }
+    }
+
+    /* the keyframe is put in its place (after the keyframes with the same time). */
+    pub fn add(&mut self, key: Keyframe<T>) {
+        let pos = self._keys.iter().position(|k| k.time > key.time).unwrap_or(self._keys.len());
+        self._keys.insert(pos, key);
+    }
+
+    /* time of the last keyframe. */
+    pub fn get_duration(&self) -> f64 {
+        self._keys.last().map(|k| k.time).unwrap_or(0f64)
+    }
+
+    /* the value at the time given, None if there are no keyframes. */
+    pub fn sample(&self, time: f64) -> Option<T> {
+        let first = self._keys.first()?;
+        if time <= first.time {
+            return Some(first.value);
+        }
+        for pair in self._keys.windows(2) {
+            let (prev, next) = (&pair[0], &pair[1]);
+            if time < next.time {
+                let t = (time - prev.time) / (next.time - prev.time);
+                return Some(prev.value.interpolate(next.value, next.easing.apply(t)));
+            }
+        }
+        self._keys.last().map(|k| k.value)
+    }
+
+    fn apply(&self, time: f64, set: impl FnOnce(T)) {
+        if let Some(value) = self.sample(time) {
+            set(value);
+        }
+    }
+}
+
+impl Animation {
+    fn get_new(target: &str) -> Animation {
+        Animation {
+            _target: target.to_string(),
+            _position: Track::get_new(),
+            _rotation: Track::get_new(),
+            _scale: Track::get_new(),
+            _color: Track::get_new(),
+        }
+    }
+
+    pub fn get_target(&self) -> &str {
+        &self._target
+    }
+
+    pub fn get_duration(&self) -> f64 {
+        self._position.get_duration()
+            .max(self._rotation.get_duration())
+            .max(self._scale.get_duration())
+            .max(self._color.get_duration())
+    }
+
+    /* put in the Figure the values of the tracks at the time given. */
+    pub fn apply(&self, fig: &mut Figure, time: f64) {
+        self._position.apply(time, |value| fig.set_position(value));
+        self._rotation.apply(time, |value| fig.set_orientation(value));
+        self._scale.apply(time, |value| fig.set_scale(value));
+        self._color.apply(time, |value| fig.set_color(value));
+    }
+}
+
+impl Timeline {
+    pub fn get_new() -> Timeline {
+        Timeline { _animations: Vec::new(), _looping: false }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self._animations.is_empty()
+    }
+
+    pub fn get_animations(&self) -> &[Animation] {
+        &self._animations
+    }
+
+    pub fn get_looping(&self) -> bool {
+        self._looping
+    }
+
+    /* if it loops, the animation starts again after the last keyframe. */
+    pub fn set_looping(&mut self, looping: bool) {
+        self._looping = looping;
+    }
+
+    /* time of the last keyframe of all the Figures. */
+    pub fn get_duration(&self) -> f64 {
+        self._animations.iter().map(|a| a.get_duration()).fold(0f64, f64::max)
+    }
+
+    fn animation_mut(&mut self, target: &str) -> &mut Animation {
+        let pos = match self._animations.iter().position(|a| a._target == target) {
+            Some(pos) => pos,
+            None => {
+                self._animations.push(Animation::get_new(target));
+                self._animations.len() - 1
+            },
+        };
+        &mut self._animations[pos]
+    }
+
+    pub fn add_position(&mut self, target: &str, time: f64, value: Vector, easing: Easing) {
+        self.animation_mut(target)._position.add(Keyframe { time, value, easing });
+    }
+
+    pub fn add_rotation(&mut self, target: &str, time: f64, value: Quaternion, easing: Easing) {
+        self.animation_mut(target)._rotation.add(Keyframe { time, value: value.normalize(), easing });
+    }
+
+    pub fn add_scale(&mut self, target: &str, time: f64, value: Vector, easing: Easing) {
+        self.animation_mut(target)._scale.add(Keyframe { time, value, easing });
+    }
+
+    pub fn add_color(&mut self, target: &str, time: f64, value: Rgb, easing: Easing) {
+        self.animation_mut(target)._color.add(Keyframe { time, value, easing });
+    }
+
+    /* the time inside the animation, after the time given (in seconds) since it started. */
+    pub fn local_time(&self, time: f64) -> f64 {
+        let duration = self.get_duration();
+        if self._looping && duration > 0f64 {
+            time.rem_euclid(duration)
+        } else {
+            time
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(x: f64) -> Vector {
+        Vector {x, y: 0f64, z: 0f64}
+    }
+
+    /* the x of the track at the time given. */
+    fn sample_x(track: &Track<Vector>, time: f64) -> f64 {
+        track.sample(time).unwrap().x
+    }
+
+    fn track(keys: &[(f64, f64, Easing)]) -> Track<Vector> {
+        let mut track = Track::get_new();
+        for &(time, x, easing) in keys {
+            track.add(Keyframe { time, value: at(x), easing });
+        }
+        track
+    }
+
+    fn close(a: f64, b: f64) -> bool {
+        (a - b).abs() < 1e-9
+    }
+
+    #[test]
+    fn easing_ends() {
+        for easing in [Easing::Linear, Easing::EaseIn, Easing::EaseOut, Easing::EaseInOut, Easing::Step] {
+            assert_eq!(easing.apply(0f64), 0f64, "{:?}", easing);
+            assert_eq!(easing.apply(1f64), 1f64, "{:?}", easing);
+            // out of the range the time is clamped.
+            assert_eq!(easing.apply(-1f64), 0f64, "{:?}", easing);
+            assert_eq!(easing.apply(2f64), 1f64, "{:?}", easing);
+        }
+        assert!(close(Easing::EaseIn.apply(0.5f64), 0.25f64));
+        assert!(close(Easing::EaseOut.apply(0.5f64), 0.75f64));
+        assert!(close(Easing::EaseInOut.apply(0.5f64), 0.5f64));
+    }
+
+    #[test]
+    fn empty_track() {
+        assert!(track(&[]).sample(1f64).is_none());
+    }
+
+    #[test]
+    fn before_the_first_and_after_the_last() {
+        let track = track(&[(1f64, 10f64, Easing::Linear), (3f64, 30f64, Easing::Linear)]);
+        assert_eq!(sample_x(&track, 0f64), 10f64);
+        assert_eq!(sample_x(&track, -5f64), 10f64);
+        assert_eq!(sample_x(&track, 3f64), 30f64);
+        assert_eq!(sample_x(&track, 100f64), 30f64);
+        assert!(close(sample_x(&track, 2f64), 20f64));
+    }
+
+    #[test]
+    fn keys_are_sorted() {
+        let track = track(&[(2f64, 20f64, Easing::Linear), (0f64, 0f64, Easing::Linear)]);
+        assert!(close(sample_x(&track, 1f64), 10f64));
+        assert_eq!(track.get_duration(), 2f64);
+    }
+
+    #[test]
+    fn two_keys_at_the_same_time_jump() {
+        let track = track(&[
+            (0f64, 0f64, Easing::Linear),
+            (1f64, 10f64, Easing::Linear),
+            (1f64, 20f64, Easing::Linear),
+            (2f64, 30f64, Easing::Linear),
+        ]);
+        assert!(close(sample_x(&track, 0.5f64), 5f64));
+        // at the time of both the last one added wins, and nothing is divided by zero.
+        assert_eq!(sample_x(&track, 1f64), 20f64);
+        assert!(close(sample_x(&track, 1.5f64), 25f64));
+    }
+
+    #[test]
+    fn step_waits_for_the_next_key() {
+        let track = track(&[(0f64, 0f64, Easing::Linear), (1f64, 10f64, Easing::Step)]);
+        assert_eq!(sample_x(&track, 0.5f64), 0f64);
+        assert_eq!(sample_x(&track, 0.999f64), 0f64);
+        assert_eq!(sample_x(&track, 1f64), 10f64);
+    }
+
+    #[test]
+    fn the_easing_of_the_second_key_is_used() {
+        let track = track(&[(0f64, 0f64, Easing::Step), (1f64, 10f64, Easing::EaseIn)]);
+        assert!(close(sample_x(&track, 0.5f64), 2.5f64));
+    }
+
+    #[test]
+    fn local_time_loops() {
+        let mut timeline = Timeline::get_new();
+        timeline.add_position("a", 0f64, at(0f64), Easing::Linear);
+        timeline.add_position("a", 4f64, at(4f64), Easing::Linear);
+        assert_eq!(timeline.local_time(5f64), 5f64);
+
+        timeline.set_looping(true);
+        assert!(close(timeline.local_time(5f64), 1f64));
+        assert!(close(timeline.local_time(9f64), 1f64));
+        assert!(close(timeline.local_time(4f64), 0f64));
+        assert!(close(timeline.local_time(3.5f64), 3.5f64));
+        assert!(close(timeline.local_time(-1f64), 3f64));
+    }
+
+    #[test]
+    fn local_time_without_duration() {
+        let mut timeline = Timeline::get_new();
+        timeline.set_looping(true);
+        assert_eq!(timeline.local_time(7f64), 7f64);
+        timeline.add_color("a", 0f64, Rgb(0, 0, 0), Easing::Linear);
+        assert_eq!(timeline.local_time(7f64), 7f64);
+    }
+}
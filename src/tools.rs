@@ -1,4 +1,13 @@
+use std::io::{Stdout, Write};
+use std::ops::{Add, Mul, Neg, Sub};
+use std::sync::Once;
 use termion::color::*;
+use termion::cursor::HideCursor;
+use termion::input::MouseTerminal;
+use termion::raw::{IntoRawMode, RawTerminal};
+use termion::screen::{AlternateScreen, IntoAlternateScreen};
+
+use crate::scene::Scene;
 
 /*
  *  mode definition. 
@@ -9,6 +18,54 @@ pub enum Writemodes {
     ToRight
 }
 
+/*
+ *  projection definition. In the Orthographic mode the depth is discarded, in the Perspective mode
+ *  the points are divided by their depth, so the farthest Figures look smaller. The "focal" is the
+ *  distance to the plane where one unit is one cell, and everything nearer than "near" is clipped.
+ * */
+#[derive(Debug, Copy, Clone)]
+pub enum Projection {
+    Orthographic,
+    Perspective { focal: f64, near: f64 },
+}
+
+/*
+ *  manner of draw the Figures. In Wireframe mode all the edges are drawed (the nearest one wins
+ *  when two of them fall in the same cell), in HiddenLine mode the faces of the Figures hide the
+ *  edges that are behind them, and in Solid mode the faces are filled with characters that are
+ *  brighter the more they look to the light.
+ * */
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum DrawMode {
+    Wireframe,
+    HiddenLine,
+    Solid,
+}
+
+/*
+ *  manner of send the frames to the terminal. In Diff mode only the cells that changed since the
+ *  last frame are printed, in FullRedraw mode the terminal is cleared and all the Window is
+ *  printed again.
+ * */
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum OutputMode {
+    Diff,
+    FullRedraw,
+}
+
+/*
+ *  resolution of the drawings. In Cell mode each cell is a point, in HalfBlock mode each cell has
+ *  two points (one over the other, with the characters '▀', '▄' and '█'), and in Braille mode each
+ *  cell has eight points (two columns of four, with the braille characters). The text is always
+ *  written in cells.
+ * */
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum RasterMode {
+    Cell,
+    HalfBlock,
+    Braille,
+}
+
 /*
  *  structs definition.
  * */
@@ -19,159 +76,604 @@ pub struct Vector {
     pub z : f64,
 }
 
+/*
+ *  transformation of the space in homogeneous coordinates, the points are columns (x, y, z, 1)
+ *  multiplied at the right of the matrix. The rows are the first index.
+ * */
+#[derive(Debug, Copy, Clone)]
+pub struct Matrix4 {
+    pub m : [[f64; 4]; 4],
+}
+
+/*
+ *  rotation in the space (w is the cosine of the half of the angle, and (x, y, z) is the axis
+ *  multiplied by the sine of the half of the angle). It must have length one to be a rotation.
+ * */
+#[derive(Debug, Copy, Clone)]
+pub struct Quaternion {
+    pub w : f64,
+    pub x : f64,
+    pub y : f64,
+    pub z : f64,
+}
+
+/*
+ *  the pose of a Figure: the vertices are scaled (each axis by its own factor), then rotated and
+ *  then moved by the translation.
+ * */
 #[derive(Debug, Copy, Clone)]
-pub struct Line {
-    _begin: Vector,
-    _end: Vector,
+pub struct Transform {
+    pub translation : Vector,
+    pub rotation : Quaternion,
+    pub scale : Vector,
 }
 
+/*
+ *  the Figure keeps its vertices once, the edges (the Lines that will be drawed) and the faces are
+ *  indexes into the vertices. The faces are polygons with its vertices in order.
+ * */
 #[derive(Debug)]
 pub struct Figure {
-    _position : Vector,
-    _lines : Vec<Line>,
-    _color : &'static dyn Color
+    _vertices : Vec<Vector>,
+    _edges : Vec<(usize, usize)>,
+    _faces : Vec<Vec<usize>>,
+    _color : Rgb,
+
+    // the pose of the Figure, applied to the vertices when it is drawed (the vertices are never
+    // changed). The initial one is the pose given when the Figure was made.
+    _transform : Transform,
+    _initial : Transform,
+
+    // how much the Figure moves in one second, and the angles that it turns in one second
+    // around x, y and z (see advance).
+    _velocity : Vector,
+    _spin : Vector,
+
+    // if there is a mode here, it is used instead of the mode of the Window.
+    _draw_mode : Option<DrawMode>,
 }
 
-#[derive(Clone)]
+/*
+ *  the camera looks from the eye to the target. The up Vector says which direction is the top of
+ *  the scream (it don't need to be perpendicular to the direction of the camera).
+ * */
+#[derive(Debug, Copy, Clone)]
+pub struct Camera {
+    _eye: Vector,
+    _target: Vector,
+    _up: Vector,
+}
+
+#[derive(Clone, PartialEq)]
 pub struct WinElem {
     _data: char,
-    _color: &'static dyn Color,
+    _color: Rgb,
+
+    // if the cell is printed in reverse video (only the text is).
+    _invert: bool,
 }
 pub struct Window {
-    // this Vectors form the plane where the Lines will be drawed.
-    _plane_vec_1: Vector,
-    _plane_vec_2: Vector,
+    // the camera from where the Figures will be drawed.
+    _camera: Camera,
+
+    // the manner of proyect the Figures in the plane.
+    _projection: Projection,
+    _draw_mode: DrawMode,
+
+    // direction where the light comes from, used in Solid mode.
+    _light: Vector,
+
+    // how many times a cell is taller than wide, the y coordinates are divided by it. If it is
+    // automatic, it is asked to the terminal.
+    _cell_aspect: f64,
+    _auto_aspect: bool,
 
     // this is the information of the Windows.
     _height: usize,
     _width: usize, 
-    _Window: Vec<WinElem>,
+    _window: Vec<WinElem>,
+
+    // the depth of what is drawed in each point, the nearest thing wins. In the modes with more
+    // than one point by cell, the points (with its color if it is on) are composed into the
+    // cells before printing.
+    _raster_mode: RasterMode,
+    _depth: Vec<f64>,
+    _dots: Vec<Option<Rgb>>,
+
+    // what the terminal is showing, to print only the differences. If the terminal is dirty its
+    // content is unknown and must be cleared before.
+    _output_mode: OutputMode,
+    _presented: Vec<WinElem>,
+    _dirty: bool,
+
+    // the terminal prepared to draw (if the Window owns it), it is restored when dropped.
+    _term: Option<Terminal>,
 }
 
+/*
+ *  terminal in raw mode, in the alternate screen, reporting the mouse and without cursor.
+ * */
+type Terminal = HideCursor<MouseTerminal<AlternateScreen<RawTerminal<Stdout>>>>;
+
 
 /*
- *  this is the lenght that a window needs to print lines. If were too large the lines dont will be
- *  printed correctly, if were too small the program will take so much time to print a line.
+ *  default camera, it looks along z with the y axis pointing to the bottom of the scream.
  * */
-static LITTLE_CONSTANT: f64 = 0.02f64;
+static EYE: Vector = Vector {x : 0f64, y : 0f64, z : 0f64};
+static TARGET: Vector = Vector {x : 0f64, y : 0f64, z : 1f64};
+static UP: Vector = Vector {x : 0f64, y : -1f64, z : 0f64};
 
 /*
- *  plane scream vectors(i will change the manner of usage).
+ *  the camera can't be closer to its target than this distance.
  * */
-static VEC_1: Vector = Vector {x : 1f64, y : 0f64, z : 0f64};
-static VEC_2: Vector = Vector {x : 0f64, y : 1f64, z : 0f64};
+static MIN_DISTANCE: f64 = 0.1f64;
+
+/*
+ *  the faces are pushed back this fraction of its depth (plus the change of depth of one cell) in
+ *  HiddenLine mode, so they don't hide their own edges.
+ * */
+static DEPTH_BIAS: f64 = 0.02f64;
+
+/*
+ *  a bit less than half a point, the positions this far from the border still are rounded inside.
+ * */
+static HALF_CELL: f64 = 0.49999f64;
+
+/*
+ *  default light, it comes from the top and from the camera.
+ * */
+static LIGHT: Vector = Vector {x : 0f64, y : -1f64, z : -1f64};
+
+/*
+ *  characters used to fill the faces, from the darkest to the brightest.
+ * */
+static LUMINANCE: &[char] = &['.', ',', '-', '~', ':', ';', '=', '!', '*', '#', '$', '@'];
+
+/*
+ *  the hook that restores the terminal when the program panics is installed only once.
+ * */
+static PANIC_HOOK: Once = Once::new();
+
+/*
+ *  what the MouseTerminal writes when it is dropped, to stop reporting the mouse.
+ * */
+static MOUSE_OFF: &str = "\x1b[?1006l\x1b[?1015l\x1b[?1002l\x1b[?1000l";
+
+/*
+ *  the cells of most of the terminals are about twice taller than wide.
+ * */
+static CELL_ASPECT: f64 = 2f64;
+
+/*
+ *  the bit of each point of a cell (by column and row), in the order of the braille characters.
+ *  The HalfBlock mode uses only the first column.
+ * */
+static POINT_BITS: [[u8; 4]; 2] = [[0x01, 0x02, 0x04, 0x40], [0x08, 0x10, 0x20, 0x80]];
+static BRAILLE_BASE: u32 = 0x2800;
+
+/*
+ *  thresholds to turn on the points of the faces in Solid mode when the Window has more than one
+ *  point by cell (ordered dithering), a face is brighter the more points it turns on.
+ * */
+static DITHER: [[f64; 4]; 4] = [
+    [0.5 / 16f64, 8.5 / 16f64, 2.5 / 16f64, 10.5 / 16f64],
+    [12.5 / 16f64, 4.5 / 16f64, 14.5 / 16f64, 6.5 / 16f64],
+    [3.5 / 16f64, 11.5 / 16f64, 1.5 / 16f64, 9.5 / 16f64],
+    [15.5 / 16f64, 7.5 / 16f64, 13.5 / 16f64, 5.5 / 16f64],
+];
 
 static PRINT_CHAR: char = '•';
-const WHITE_COLOR: &dyn Color = &Rgb(255, 255, 255);
-const EMPTY_CELL: WinElem = WinElem {_data: ' ', _color: WHITE_COLOR};
+const WHITE_COLOR: Rgb = Rgb(255, 255, 255);
+const EMPTY_CELL: WinElem = WinElem {_data: ' ', _color: WHITE_COLOR, _invert: false};
 
 
+/*
+ *  functions.
+ * */
+
+/* normal of the polygon (Newell method), it works with polygons that are not exactly flat. */
+fn polygon_normal(poly: &[Vector]) -> Vector {
+    let mut normal = Vector {x: 0f64, y: 0f64, z: 0f64};
+    for i in 0..poly.len() {
+        let cur = poly[i];
+        let next = poly[(i + 1) % poly.len()];
+        normal.x += (cur.y - next.y) * (cur.z + next.z);
+        normal.y += (cur.z - next.z) * (cur.x + next.x);
+        normal.z += (cur.x - next.x) * (cur.y + next.y);
+    }
+    normal.normalize()
+}
+
+/* how much the depth of the triangle (in cells) changes from one cell to the next one. */
+fn depth_slope(tri: [Vector; 3]) -> f64 {
+    let [a, b, c] = tri;
+    let area = (b.x - a.x)*(c.y - a.y) - (b.y - a.y)*(c.x - a.x);
+    if area.abs() < f64::EPSILON {
+        return 0f64;
+    }
+    let dzdx = ((b.z - a.z)*(c.y - a.y) - (c.z - a.z)*(b.y - a.y)) / area;
+    let dzdy = ((c.z - a.z)*(b.x - a.x) - (b.z - a.z)*(c.x - a.x)) / area;
+    dzdx.abs() + dzdy.abs()
+}
+
+/* the sequence that starts or ends the reverse video. */
+fn style_of(invert: bool) -> &'static str {
+    if invert { termion::style::Invert.as_ref() } else { termion::style::NoInvert.as_ref() }
+}
+
 /*
  *  struct implementation.
  * */
 impl Vector {
-    pub fn sum(self, vect: Vector) -> Vector {
+    pub fn scalar_prod(&self, vect: Vector) -> f64 {
+        self.x*vect.x + self.y*vect.y + self.z*vect.z 
+    }
+
+    pub fn vector_prod(&self, vect: Vector) -> Vector {
+        Vector {
+            x : self.y*vect.z - self.z*vect.y,
+            y : self.z*vect.x - self.x*vect.z,
+            z : self.x*vect.y - self.y*vect.x,
+        }
+    }
+
+    pub fn length(&self) -> f64 {
+        self.scalar_prod(*self).sqrt()
+    }
+
+    pub fn normalize(self) -> Vector {
+        let len = self.length();
+        if len == 0f64 { self } else { self * (1f64 / len) }
+    }
+}
+
+impl Add for Vector {
+    type Output = Vector;
+
+    fn add(self, vect: Vector) -> Vector {
         Vector {
             x : self.x + vect.x,
             y : self.y + vect.y,
             z : self.z + vect.z,
         }
     }
-    
-    pub fn mul(self, coef: f64) -> Vector {
+}
+
+impl Sub for Vector {
+    type Output = Vector;
+
+    fn sub(self, vect: Vector) -> Vector {
+        Vector {
+            x : self.x - vect.x,
+            y : self.y - vect.y,
+            z : self.z - vect.z,
+        }
+    }
+}
+
+impl Mul<f64> for Vector {
+    type Output = Vector;
+
+    fn mul(self, coef: f64) -> Vector {
         Vector {
             x : self.x * coef,
             y : self.y * coef,
             z : self.z * coef,
         }
     }
-    
-    pub fn scalar_prod(&self, vect: Vector) -> f64 {
-        self.x*vect.x + self.y*vect.y + self.z*vect.z 
+}
+
+impl Neg for Vector {
+    type Output = Vector;
+
+    fn neg(self) -> Vector {
+        self * -1f64
     }
 }
 
-impl Line { 
-    fn rotate_in_x(&mut self, alpha: f64) {
-        let sin = alpha.sin();
-        let cos = alpha.cos();
-        
-        let old_y = self._begin.y;
-        let old_z = self._begin.z;
-        self._begin.y = old_y*cos - old_z*sin;
-        self._begin.z = old_y*sin + old_z*cos;
-        
-        let old_y = self._end.y;
-        let old_z = self._end.z;
-        self._end.y = old_y*cos - old_z*sin;
-        self._end.z = old_y*sin + old_z*cos;
+impl Matrix4 {
+    pub fn identity() -> Matrix4 {
+        Matrix4::scale(Vector {x: 1f64, y: 1f64, z: 1f64})
     }
-    
-    fn rotate_in_y(&mut self, alpha: f64) {
-        let sin = alpha.sin();
-        let cos = alpha.cos();
-        
-        let old_x = self._begin.x;
-        let old_z = self._begin.z;
-        self._begin.x = old_x*cos - old_z*sin;
-        self._begin.z = old_x*sin + old_z*cos;
-        
-        let old_x = self._end.x;
-        let old_z = self._end.z;
-        self._end.x = old_x*cos - old_z*sin;
-        self._end.z = old_x*sin + old_z*cos;
+
+    pub fn translation(vect: Vector) -> Matrix4 {
+        Matrix4 {
+            m : [
+                [1f64, 0f64, 0f64, vect.x],
+                [0f64, 1f64, 0f64, vect.y],
+                [0f64, 0f64, 1f64, vect.z],
+                [0f64, 0f64, 0f64, 1f64],
+            ],
+        }
     }
-    
-    fn rotate_in_z(&mut self, alpha: f64) {
-        let sin = alpha.sin();
-        let cos = alpha.cos();
-        
-        let old_x = self._begin.x;
-        let old_y = self._begin.y;
-        self._begin.x = old_x*cos - old_y*sin;
-        self._begin.y = old_x*sin + old_y*cos;
-        
-        let old_x = self._end.x;
-        let old_y = self._end.y;
-        self._end.x = old_x*cos - old_y*sin;
-        self._end.y = old_x*sin + old_y*cos;
+
+    /* each coordinate of the Vector is the factor of its axis. */
+    pub fn scale(vect: Vector) -> Matrix4 {
+        Matrix4 {
+            m : [
+                [vect.x, 0f64, 0f64, 0f64],
+                [0f64, vect.y, 0f64, 0f64],
+                [0f64, 0f64, vect.z, 0f64],
+                [0f64, 0f64, 0f64, 1f64],
+            ],
+        }
+    }
+
+    /* the same rotation of the Quaternion (it must have length one). */
+    pub fn rotation(quat: Quaternion) -> Matrix4 {
+        let Quaternion {w, x, y, z} = quat;
+        Matrix4 {
+            m : [
+                [1f64 - 2f64*(y*y + z*z), 2f64*(x*y - w*z), 2f64*(x*z + w*y), 0f64],
+                [2f64*(x*y + w*z), 1f64 - 2f64*(x*x + z*z), 2f64*(y*z - w*x), 0f64],
+                [2f64*(x*z - w*y), 2f64*(y*z + w*x), 1f64 - 2f64*(x*x + y*y), 0f64],
+                [0f64, 0f64, 0f64, 1f64],
+            ],
+        }
+    }
+
+    /* rotation of alpha radians around the axis (that pass through the origin). */
+    pub fn rotation_around(axis: Vector, alpha: f64) -> Matrix4 {
+        Matrix4::rotation(Quaternion::from_axis_angle(axis, alpha))
+    }
+
+    /* the matrix whose rows are the Vectors, it expresses the points in the coordinates of the
+     * three directions (that must be perpendicular and of length one) with the origin moved to
+     * the center. */
+    pub fn change_of_basis(rows: [Vector; 3], center: Vector) -> Matrix4 {
+        let mut result = Matrix4::identity();
+        for (row, vect) in result.m.iter_mut().zip(rows.iter()) {
+            *row = [vect.x, vect.y, vect.z, -vect.scalar_prod(center)];
+        }
+        result
+    }
+
+    /* the point is multiplied with a fourth coordinate equal to 1, and the result is divided by
+     * the fourth coordinate obtained (so the matrices can divide by the depth). */
+    pub fn transform_point(&self, vect: Vector) -> Vector {
+        let coords = [vect.x, vect.y, vect.z, 1f64];
+        let row = |i: usize| (0..4).map(|j| self.m[i][j] * coords[j]).sum::<f64>();
+        let w = row(3);
+        Vector {x: row(0) / w, y: row(1) / w, z: row(2) / w}
+    }
+
+    /* the directions are not moved, only the part 3x3 of the matrix is used. */
+    pub fn transform_direction(&self, vect: Vector) -> Vector {
+        let row = |i: usize| self.m[i][0]*vect.x + self.m[i][1]*vect.y + self.m[i][2]*vect.z;
+        Vector {x: row(0), y: row(1), z: row(2)}
     }
 }
 
-impl Figure {
-    pub fn cube(dimention: f64, _position: Vector, _color: &'static dyn Color) -> Figure {
-        let dim = dimention / 2f64;
-        let mut vects : Vec<Vector> = vec![
-            Vector {x: dim, y: dim, z: dim}, 
-            Vector {x: dim, y: -dim, z: -dim}, 
-            Vector {x: -dim, y: dim, z: -dim}, 
-            Vector {x: -dim, y: -dim, z: dim}
-        ];
+/* the transformation made by mat and then by self. */
+impl Mul for Matrix4 {
+    type Output = Matrix4;
 
-        // make all the Lines and bring them into lines.
-        let mut lines : Vec<Line> = Vec::new();
-        while let Some(p) = vects.pop() {
-            let mut q = p;
-            q.x = -q.x;
-            lines.push(Line {_begin: p, _end: q});
-            
-            q.x = -q.x;
-            q.y = -q.y;
-            lines.push(Line {_begin: p, _end: q});
+    fn mul(self, mat: Matrix4) -> Matrix4 {
+        let mut result = Matrix4 {m: [[0f64; 4]; 4]};
+        for i in 0..4 {
+            for j in 0..4 {
+                result.m[i][j] = (0..4).map(|k| self.m[i][k] * mat.m[k][j]).sum();
+            }
+        }
+        result
+    }
+}
 
-            q.y = -q.y;
-            q.z = -q.z;
-            lines.push(Line {_begin: p, _end: q});
+impl Quaternion {
+    pub fn identity() -> Quaternion {
+        Quaternion {w: 1f64, x: 0f64, y: 0f64, z: 0f64}
+    }
+
+    /* rotation of alpha radians around the axis (it don't need to be normalized). */
+    pub fn from_axis_angle(axis: Vector, alpha: f64) -> Quaternion {
+        let axis = axis.normalize();
+        let sin = (alpha / 2f64).sin();
+        Quaternion {
+            w : (alpha / 2f64).cos(),
+            x : axis.x * sin,
+            y : axis.y * sin,
+            z : axis.z * sin,
         }
+    }
 
+    /* rotation around x, then around y and then around z (the axes don't move with the
+     * rotations). */
+    pub fn from_euler(x: f64, y: f64, z: f64) -> Quaternion {
+        let qx = Quaternion::from_axis_angle(Vector {x: 1f64, y: 0f64, z: 0f64}, x);
+        let qy = Quaternion::from_axis_angle(Vector {x: 0f64, y: 1f64, z: 0f64}, y);
+        let qz = Quaternion::from_axis_angle(Vector {x: 0f64, y: 0f64, z: 1f64}, z);
+        qz.product(qy).product(qx)
+    }
+
+    /* the rotation made by quat and then by self. */
+    pub fn product(self, quat: Quaternion) -> Quaternion {
+        Quaternion {
+            w : self.w*quat.w - self.x*quat.x - self.y*quat.y - self.z*quat.z,
+            x : self.w*quat.x + self.x*quat.w + self.y*quat.z - self.z*quat.y,
+            y : self.w*quat.y - self.x*quat.z + self.y*quat.w + self.z*quat.x,
+            z : self.w*quat.z + self.x*quat.y - self.y*quat.x + self.z*quat.w,
+        }
+    }
+
+    /* the products accumulate errors, with this the Quaternion is a rotation again. */
+    pub fn normalize(self) -> Quaternion {
+        let len = (self.w*self.w + self.x*self.x + self.y*self.y + self.z*self.z).sqrt();
+        if len == 0f64 {
+            return Quaternion::identity();
+        }
+        Quaternion {w: self.w / len, x: self.x / len, y: self.y / len, z: self.z / len}
+    }
+
+    pub fn rotate(&self, vect: Vector) -> Vector {
+        let axis = Vector {x: self.x, y: self.y, z: self.z};
+        let t = axis.vector_prod(vect) * 2f64;
+        vect + t * self.w + axis.vector_prod(t)
+    }
+}
+
+impl Transform {
+    /* the pose that leaves the vertices as they are. */
+    pub fn identity() -> Transform {
+        Transform {
+            translation : Vector {x: 0f64, y: 0f64, z: 0f64},
+            rotation : Quaternion::identity(),
+            scale : Vector {x: 1f64, y: 1f64, z: 1f64},
+        }
+    }
+
+    /* the scale, the rotation and the translation in one matrix (in that order). */
+    pub fn matrix(&self) -> Matrix4 {
+        Matrix4::translation(self.translation)
+            * Matrix4::rotation(self.rotation)
+            * Matrix4::scale(self.scale)
+    }
+
+    pub fn apply(&self, vect: Vector) -> Vector {
+        self.matrix().transform_point(vect)
+    }
+}
+
+impl Default for Camera {
+    fn default() -> Camera {
+        Camera::get_new(EYE, TARGET, UP)
+    }
+}
+
+impl Camera {
+    pub fn get_new(_eye: Vector, _target: Vector, _up: Vector) -> Camera {
+        Camera { _eye, _target, _up }
+    }
+
+    pub fn get_eye(&self) -> Vector {
+        self._eye
+    }
+
+    pub fn get_target(&self) -> Vector {
+        self._target
+    }
+
+    pub fn look_at(&mut self, target: Vector) {
+        self._target = target;
+    }
+
+    /* the Vectors that point to the right of the scream, to the bottom of the scream and to
+     * where the camera looks. */
+    fn basis(&self) -> (Vector, Vector, Vector) {
+        let forward = (self._target - self._eye).normalize();
+        let right = forward.vector_prod(self._up).normalize();
+        let down = forward.vector_prod(right);
+        (right, down, forward)
+    }
+
+    /* the matrix that expresses the points in the coordinates of the camera, the third
+     * coordinate is the depth (the distance to the plane of the camera). The directions are
+     * expressed with transform_direction, so they are not moved to the eye. */
+    pub fn view_matrix(&self) -> Matrix4 {
+        let (right, down, forward) = self.basis();
+        Matrix4::change_of_basis([right, down, forward], self._eye)
+    }
+
+    /* move the eye around the target, yaw turns around the up Vector and pitch goes to the top or
+     * the bottom. The pitch stops before the camera looks along the up Vector. */
+    pub fn orbit(&mut self, yaw: f64, pitch: f64) {
+        let (right, _, _) = self.basis();
+        let yawed = Matrix4::rotation_around(self._up, yaw);
+        let pitched = Matrix4::rotation_around(yawed.transform_direction(right), pitch) * yawed;
+        let offset = self._eye - self._target;
+        let cos_up = pitched.transform_direction(offset).normalize().scalar_prod(self._up.normalize());
+        let turn = if cos_up.abs() < 0.99f64 { pitched } else { yawed };
+        self._eye = self._target + turn.transform_direction(offset);
+    }
+
+    /* move the eye and the target along the plane of the scream. */
+    pub fn pan(&mut self, dx: f64, dy: f64) {
+        let (right, down, _) = self.basis();
+        let delta = right * dx + down * dy;
+        self._eye = self._eye + delta;
+        self._target = self._target + delta;
+    }
+
+    /* move the eye to the target (or away if the distance is negative), without passing it. */
+    pub fn dolly(&mut self, distance: f64) {
+        let offset = self._target - self._eye;
+        let len = offset.length();
+        let distance = distance.min(len - MIN_DISTANCE);
+        self._eye = self._eye + offset.normalize() * distance;
+    }
+}
+
+impl Projection {
+    pub fn perspective(focal: f64, near: f64) -> Projection {
+        Projection::Perspective { focal, near }
+    }
+
+    /* the field of view is the horizontal angle (in radians) that the window of the given width
+     * will show. */
+    pub fn from_fov(fov: f64, width: usize, near: f64) -> Projection {
+        let focal = (width as f64 / 2f64) / (fov / 2f64).tan();
+        Projection::Perspective { focal, near }
+    }
+}
+
+impl Figure {
+    /* make a Figure from its vertices, edges and faces. The indexes of the edges and the faces
+     * must be valid indexes of the vertices. */
+    pub fn mesh(
+        _vertices: Vec<Vector>,
+        _edges: Vec<(usize, usize)>,
+        _faces: Vec<Vec<usize>>,
+        _position: Vector,
+        _color: Rgb
+    ) -> Figure {
+        let _transform = Transform {translation: _position, ..Transform::identity()};
         Figure {
-            _lines: lines,
-            _position,
-            _color
+            _vertices,
+            _edges,
+            _faces,
+            _color,
+            _transform,
+            _initial: _transform,
+            _velocity: Vector {x: 0f64, y: 0f64, z: 0f64},
+            _spin: Vector {x: 0f64, y: 0f64, z: 0f64},
+            _draw_mode: None,
         }
     }
 
-    pub fn ruby(dimention: f64, _position: Vector, _color: &'static dyn Color) -> Figure { 
+    pub fn cube(dimention: f64, _position: Vector, _color: Rgb) -> Figure {
+        let dim = dimention / 2f64;
+
+        // the bits of the index say the sign of each coordinate (1 for x, 2 for y, 4 for z).
+        let vect: Vec<Vector> = (0..8usize)
+            .map(|i| Vector {
+                x: if i & 1 != 0 { dim } else { -dim },
+                y: if i & 2 != 0 { dim } else { -dim },
+                z: if i & 4 != 0 { dim } else { -dim },
+            })
+            .collect();
+
+        // the edges join the vertices that differ in only one coordinate.
+        let mut edges: Vec<(usize, usize)> = Vec::new();
+        for i in 0..8usize {
+            for bit in [1usize, 2, 4] {
+                if i & bit == 0 {
+                    edges.push((i, i | bit));
+                }
+            }
+        }
+
+        let faces: Vec<Vec<usize>> = vec![
+            vec![0, 2, 6, 4],
+            vec![1, 5, 7, 3],
+            vec![0, 4, 5, 1],
+            vec![2, 3, 7, 6],
+            vec![0, 1, 3, 2],
+            vec![4, 6, 7, 5],
+        ];
+
+        Figure::mesh(vect, edges, faces, _position, _color)
+    }
+
+    pub fn ruby(dimention: f64, _position: Vector, _color: Rgb) -> Figure { 
         let dim = dimention / 2f64;
         let med =  dim * 3f64 / 4f64;
         let vect : Vec<Vector> = vec![
@@ -183,29 +685,21 @@ impl Figure {
             Vector {x: 0f64, y: -dim/2f64, z: 0f64}, 
         ];
         
-        let mut lines: Vec<Line> = Vec::new();
-
-        lines.push(Line {_begin: vect[0], _end: vect[1]});
-        lines.push(Line {_begin: vect[0], _end: vect[2]});
-        lines.push(Line {_begin: vect[0], _end: vect[3]});
-        lines.push(Line {_begin: vect[0], _end: vect[4]});
-        lines.push(Line {_begin: vect[1], _end: vect[5]});
-        lines.push(Line {_begin: vect[2], _end: vect[5]});
-        lines.push(Line {_begin: vect[3], _end: vect[5]});
-        lines.push(Line {_begin: vect[4], _end: vect[5]});
-        lines.push(Line {_begin: vect[1], _end: vect[2]});
-        lines.push(Line {_begin: vect[2], _end: vect[3]});
-        lines.push(Line {_begin: vect[3], _end: vect[4]});
-        lines.push(Line {_begin: vect[4], _end: vect[1]});
+        let edges: Vec<(usize, usize)> = vec![
+            (0, 1), (0, 2), (0, 3), (0, 4),
+            (1, 5), (2, 5), (3, 5), (4, 5),
+            (1, 2), (2, 4), (4, 3), (3, 1),
+        ];
 
-        Figure {
-            _lines: lines,
-            _position,
-            _color
-        }
+        let faces: Vec<Vec<usize>> = vec![
+            vec![0, 1, 2], vec![0, 2, 4], vec![0, 4, 3], vec![0, 3, 1],
+            vec![5, 2, 1], vec![5, 4, 2], vec![5, 3, 4], vec![5, 1, 3],
+        ];
+
+        Figure::mesh(vect, edges, faces, _position, _color)
     }
 
-    pub fn pyramid(dimention: f64, _position: Vector, _color: &'static dyn Color) -> Figure { 
+    pub fn pyramid(dimention: f64, _position: Vector, _color: Rgb) -> Figure { 
         let dcos = dimention * (std::f64::consts::PI / 3f64).cos();
         let dsen = dimention * (std::f64::consts::PI / 3f64).sin();
         let vect : Vec<Vector> = vec![
@@ -215,23 +709,19 @@ impl Figure {
             Vector {x: dimention, y: 0f64, z: 0f64}, 
         ];
         
-        let mut lines: Vec<Line> = Vec::new();
-
-        lines.push(Line {_begin: vect[0], _end: vect[1]});
-        lines.push(Line {_begin: vect[0], _end: vect[2]});
-        lines.push(Line {_begin: vect[0], _end: vect[3]});
-        lines.push(Line {_begin: vect[1], _end: vect[2]});
-        lines.push(Line {_begin: vect[1], _end: vect[3]});
-        lines.push(Line {_begin: vect[2], _end: vect[3]});
+        let edges: Vec<(usize, usize)> = vec![
+            (0, 1), (0, 2), (0, 3),
+            (1, 2), (1, 3), (2, 3),
+        ];
+
+        let faces: Vec<Vec<usize>> = vec![
+            vec![0, 1, 2], vec![0, 2, 3], vec![0, 3, 1], vec![1, 3, 2],
+        ];
         
-        Figure {
-            _lines: lines,
-            _position,
-            _color
-        }
+        Figure::mesh(vect, edges, faces, _position, _color)
     }
 
-    pub fn square(height: usize, width: usize, _position: Vector, _color: &'static dyn Color) -> Figure {
+    pub fn square(height: usize, width: usize, _position: Vector, _color: Rgb) -> Figure {
         let wby2: f64 = (width / 2) as f64;
         let hby2: f64 = (height / 2) as f64;
         let vect: Vec<Vector> = vec![
@@ -241,66 +731,273 @@ impl Figure {
             Vector {x:  wby2, y: -hby2, z: 0f64}, 
         ];
         
-        let mut lines: Vec<Line> = Vec::new();
-
-        lines.push(Line {_begin: vect[0], _end: vect[1]});
-        lines.push(Line {_begin: vect[1], _end: vect[2]});
-        lines.push(Line {_begin: vect[2], _end: vect[3]});
-        lines.push(Line {_begin: vect[3], _end: vect[0]});
+        let edges: Vec<(usize, usize)> = vec![(0, 1), (1, 2), (2, 3), (3, 0)];
+        let faces: Vec<Vec<usize>> = vec![vec![0, 1, 2, 3]];
         
-        Figure {
-            _lines: lines,
-            _position,
-            _color
-        }
+        Figure::mesh(vect, edges, faces, _position, _color)
+    }
+
+    pub fn get_vertices(&self) -> &[Vector] {
+        &self._vertices
+    }
+
+    pub fn get_edges(&self) -> &[(usize, usize)] {
+        &self._edges
+    }
+
+    pub fn get_faces(&self) -> &[Vec<usize>] {
+        &self._faces
+    }
+
+    pub fn get_color(&self) -> Rgb {
+        self._color
+    }
+
+    pub fn set_color(&mut self, color: Rgb) {
+        self._color = color;
+    }
+
+    pub fn get_draw_mode(&self) -> Option<DrawMode> {
+        self._draw_mode
+    }
+
+    /* with None the Figure is drawed in the mode of the Window. */
+    pub fn set_draw_mode(&mut self, mode: Option<DrawMode>) {
+        self._draw_mode = mode;
+    }
+
+    pub fn get_transform(&self) -> Transform {
+        self._transform
+    }
+
+    pub fn set_transform(&mut self, transform: Transform) {
+        self._transform = Transform {rotation: transform.rotation.normalize(), ..transform};
+    }
+
+    /* go back to the pose that the Figure had when it was made. */
+    pub fn reset_transform(&mut self) {
+        self._transform = self._initial;
+    }
+
+    pub fn get_position(&self) -> Vector {
+        self._transform.translation
+    }
+
+    pub fn set_position(&mut self, position: Vector) {
+        self._transform.translation = position;
+    }
+
+    pub fn translate(&mut self, delta: Vector) {
+        self._transform.translation = self._transform.translation + delta;
+    }
+
+    pub fn get_velocity(&self) -> Vector {
+        self._velocity
+    }
+
+    pub fn set_velocity(&mut self, velocity: Vector) {
+        self._velocity = velocity;
+    }
+
+    pub fn get_spin(&self) -> Vector {
+        self._spin
+    }
+
+    /* the angles are the ones of rotate_in_x, rotate_in_y and rotate_in_z. */
+    pub fn set_spin(&mut self, spin: Vector) {
+        self._spin = spin;
+    }
+
+    /* move and turn the Figure as much as its velocity and its spin say for the time given (in
+     * seconds). */
+    pub fn advance(&mut self, seconds: f64) {
+        self.translate(self._velocity * seconds);
+        self.rotate_in_y(self._spin.y * seconds);
+        self.rotate_in_x(self._spin.x * seconds);
+        self.rotate_in_z(self._spin.z * seconds);
+    }
+
+    pub fn get_scale(&self) -> Vector {
+        self._transform.scale
+    }
+
+    /* each coordinate is the factor of its axis (in the coordinates of the Figure). */
+    pub fn set_scale(&mut self, scale: Vector) {
+        self._transform.scale = scale;
+    }
+
+    pub fn get_orientation(&self) -> Quaternion {
+        self._transform.rotation
+    }
+
+    pub fn set_orientation(&mut self, orientation: Quaternion) {
+        self._transform.rotation = orientation.normalize();
+    }
+
+    /* set the orientation with the angles around x, y and z (see Quaternion::from_euler). */
+    pub fn set_euler(&mut self, x: f64, y: f64, z: f64) {
+        self._transform.rotation = Quaternion::from_euler(x, y, z);
+    }
+
+    /* rotate the Figure around the axis that pass through its position. The rotation is added to
+     * the orientation, the vertices are not changed. */
+    pub fn rotate_around(&mut self, axis: Vector, alpha: f64) {
+        self._transform.rotation = Quaternion::from_axis_angle(axis, alpha)
+            .product(self._transform.rotation)
+            .normalize();
     }
 
     pub fn rotate_in_x(&mut self, alpha: f64) {
-        for vect in &mut self._lines {
-            vect.rotate_in_x(alpha);
-        }
+        self.rotate_around(Vector {x: 1f64, y: 0f64, z: 0f64}, alpha);
     }
     
+    // it turns from x to z, like it always did.
     pub fn rotate_in_y(&mut self, alpha: f64) {
-        for vect in &mut self._lines {
-            vect.rotate_in_y(alpha);
-        }
+        self.rotate_around(Vector {x: 0f64, y: -1f64, z: 0f64}, alpha);
     }
     
     pub fn rotate_in_z(&mut self, alpha: f64) {
-        for vect in &mut self._lines {
-            vect.rotate_in_z(alpha);
-        }
+        self.rotate_around(Vector {x: 0f64, y: 0f64, z: 1f64}, alpha);
     }
 }
 
 impl Window {
+    /* a Window that prints in the terminal as it is. */
     pub fn get_new(_height: usize, _width: usize) -> Window {
         Window {
-            _plane_vec_1: VEC_1,
-            _plane_vec_2: VEC_2,
+            _camera: Camera::default(),
+            _projection: Projection::Orthographic,
+            _draw_mode: DrawMode::Wireframe,
+            _light: LIGHT,
+            _cell_aspect: CELL_ASPECT,
+            _auto_aspect: false,
             _height,
             _width,
-            _Window: vec![EMPTY_CELL; _height * _width],
+            _window: vec![EMPTY_CELL; _height * _width],
+            _raster_mode: RasterMode::Cell,
+            _depth: vec![f64::INFINITY; _height * _width],
+            _dots: Vec::new(),
+            _output_mode: OutputMode::Diff,
+            _presented: vec![EMPTY_CELL; _height * _width],
+            _dirty: true,
+            _term: None,
         }
     }
     
+    /* a Window as big as the terminal, that takes the terminal: it goes to the alternate screen,
+     * hides the cursor, enables the raw mode and asks the terminal to report the mouse. Everything
+     * is restored when the Window is dropped, and also if the program panics. */
     pub fn get_new_term_size() -> Window {
         let dims = match termion::terminal_size() {
             Ok(result) => result,
             Err(_) => panic!("Was imposible to get the dimentions of the termianl."),
         };
-        let h = dims.1 as usize - 1;
-        let w = dims.0 as usize;
-        Window {
-            _plane_vec_1: VEC_1,
-            _plane_vec_2: VEC_2,
-            _height: h,
-            _width: w,
-            _Window: vec![EMPTY_CELL; h * w],
+        // the settings of the terminal before the raw mode, so the panic hook can put them back
+        // (the RawTerminal that does it when it is dropped may be in other thread).
+        // SAFETY: termios is a struct of numbers, and tcgetattr fills it.
+        let mut saved: libc::termios = unsafe { std::mem::zeroed() };
+        let saved = match unsafe { libc::tcgetattr(libc::STDOUT_FILENO, &mut saved) } {
+            0 => Some(saved),
+            _ => None,
+        };
+        let term = std::io::stdout()
+            .into_raw_mode()
+            .and_then(|raw| raw.into_alternate_screen())
+            .map(MouseTerminal::from)
+            .map(HideCursor::from);
+        let term = match term {
+            Ok(result) => result,
+            Err(_) => panic!("Was imposible to prepare the terminal."),
+        };
+        PANIC_HOOK.call_once(|| {
+            let default_hook = std::panic::take_hook();
+            std::panic::set_hook(Box::new(move |info| {
+                let mut out = std::io::stdout();
+                let _ = write!(out, "{}{}{}", MOUSE_OFF, termion::screen::ToMainScreen, termion::cursor::Show);
+                let _ = out.flush();
+                if let Some(saved) = saved {
+                    // SAFETY: the pointer is to the termios that was got at the start.
+                    unsafe { libc::tcsetattr(libc::STDOUT_FILENO, libc::TCSANOW, &saved) };
+                }
+                default_hook(info);
+            }));
+        });
+
+        let mut win = Window::get_new((dims.1 as usize).saturating_sub(1), dims.0 as usize);
+        win._term = Some(term);
+        win._auto_aspect = true;
+        win.detect_cell_aspect();
+        win
+    }
+
+    /* ask the terminal its size in pixels to know the form of the cells. Not all the terminals
+     * answer, in that case the aspect is not changed. */
+    fn detect_cell_aspect(&mut self) {
+        let (Ok((cols, rows)), Ok((px_w, px_h))) = (termion::terminal_size(), termion::terminal_size_pixels()) else {
+            return;
+        };
+        if cols > 0 && rows > 0 && px_w > 0 && px_h > 0 {
+            self._cell_aspect = (px_h as f64 / rows as f64) / (px_w as f64 / cols as f64);
         }
     }
 
+    /* change the size of the Window, what was drawed is lost and the terminal will be cleared in
+     * the next print. */
+    pub fn resize(&mut self, _height: usize, _width: usize) {
+        self._height = _height;
+        self._width = _width;
+        self._window = vec![EMPTY_CELL; _height * _width];
+        self._presented = vec![EMPTY_CELL; _height * _width];
+        self._dirty = true;
+        self.alloc_points();
+    }
+
+    /* the points by cell (in width and height) of the raster mode. */
+    fn sub_cells(&self) -> (usize, usize) {
+        match self._raster_mode {
+            RasterMode::Cell => (1, 1),
+            RasterMode::HalfBlock => (1, 2),
+            RasterMode::Braille => (2, 4),
+        }
+    }
+
+    /* the points of the Window (in width and height). */
+    fn grid_size(&self) -> (usize, usize) {
+        let (sx, sy) = self.sub_cells();
+        (self._width * sx, self._height * sy)
+    }
+
+    fn alloc_points(&mut self) {
+        let (gw, gh) = self.grid_size();
+        self._depth = vec![f64::INFINITY; gw * gh];
+        self._dots = match self._raster_mode {
+            RasterMode::Cell => Vec::new(),
+            _ => vec![None; gw * gh],
+        };
+    }
+
+    /* if the Window owns the terminal and the terminal changed its size, the Window takes the new
+     * size. Returns if the size changed. */
+    pub fn follow_term_size(&mut self) -> bool {
+        if self._term.is_none() {
+            return false;
+        }
+        let (w, h) = match termion::terminal_size() {
+            Ok(result) => result,
+            Err(_) => return false,
+        };
+        let h = (h as usize).saturating_sub(1);
+        let w = w as usize;
+        if h == self._height && w == self._width {
+            return false;
+        }
+        self.resize(h, w);
+        if self._auto_aspect {
+            self.detect_cell_aspect();
+        }
+        true
+    }
+
     pub fn get_height(&self) -> usize {
         self._height
     }
@@ -309,103 +1006,680 @@ impl Window {
         self._width
     }
 
+    /* the characters of a row and which of them are in reverse video, to check what is written. */
+    #[cfg(test)]
+    pub fn get_row(&self, row: usize) -> (String, Vec<bool>) {
+        let cells = &self._window[row*self._width..(row + 1)*self._width];
+        (cells.iter().map(|elem| elem._data).collect(), cells.iter().map(|elem| elem._invert).collect())
+    }
+
+    pub fn get_camera_mut(&mut self) -> &mut Camera {
+        &mut self._camera
+    }
+
+    pub fn set_camera(&mut self, camera: Camera) {
+        self._camera = camera;
+    }
+
+    pub fn set_projection(&mut self, projection: Projection) {
+        self._projection = projection;
+    }
+
+    pub fn set_draw_mode(&mut self, mode: DrawMode) {
+        self._draw_mode = mode;
+    }
+
+    pub fn get_output_mode(&self) -> OutputMode {
+        self._output_mode
+    }
+
+    pub fn set_output_mode(&mut self, mode: OutputMode) {
+        self._output_mode = mode;
+        self._dirty = true;
+    }
+
+    /* what was drawed with the old mode is lost. */
+    pub fn set_raster_mode(&mut self, mode: RasterMode) {
+        if mode != self._raster_mode {
+            self._raster_mode = mode;
+            self.alloc_points();
+        }
+    }
+
+    /* clear the Window to draw the next frame. The terminal is not cleared, the next print will
+     * erase what is not drawed again (so the last frame is shown until then). The size of the
+     * terminal is checked here, so the next frame is drawed with the new size. */
     pub fn clear(&mut self) {
-        clearscreen::clear().expect("failed to clear the screen.");
+        self.follow_term_size();
+        self._window.fill(EMPTY_CELL);
+        self._depth.fill(f64::INFINITY);
+        self._dots.fill(None);
+    }
+
+    pub fn fill(&mut self, character: char) {
         for i in 0..self._height {
             for j in 0..self._width {
-                self._Window[i*self._width + j] = EMPTY_CELL;
+                self._window[i*self._width + j] = WinElem{_data: character, _color: WHITE_COLOR, _invert: false};
             }
         }
     }
+    
+    pub fn print(&mut self) {
+        self.compose();
+        let result = match self._output_mode {
+            OutputMode::Diff => self.diff_frame(),
+            OutputMode::FullRedraw => self.full_frame(),
+        };
+        let printed = match self._term {
+            Some(ref mut term) => term.write_all(result.as_bytes()).and_then(|_| term.flush()),
+            None => {
+                let mut out = std::io::stdout().lock();
+                out.write_all(result.as_bytes()).and_then(|_| out.flush())
+            },
+        };
+        printed.expect("failed to print the window.");
+    }
 
-    pub fn fill(&mut self, character: char) {
+    /* put the points in the cells (only in HalfBlock and Braille modes). The cells with text are
+     * kept, and the color of a cell is the color of its nearest point. */
+    fn compose(&mut self) {
+        let (sx, sy) = self.sub_cells();
+        if self._raster_mode == RasterMode::Cell {
+            return;
+        }
+        let (gw, _) = self.grid_size();
+        for cy in 0..self._height {
+            for cx in 0..self._width {
+                let mut bits: u8 = 0;
+                let mut color: Option<Rgb> = None;
+                let mut nearest = f64::INFINITY;
+                let mut text = false;
+                for (dx, column) in POINT_BITS.iter().enumerate().take(sx) {
+                    for (dy, bit) in column.iter().enumerate().take(sy) {
+                        let pos = cx*sx + dx + gw*(cy*sy + dy);
+                        text |= self._depth[pos] == f64::NEG_INFINITY;
+                        if let Some(dot_color) = self._dots[pos] {
+                            bits |= bit;
+                            if self._depth[pos] < nearest || color.is_none() {
+                                nearest = self._depth[pos];
+                                color = Some(dot_color);
+                            }
+                        }
+                    }
+                }
+                if let (false, Some(color)) = (text, color) {
+                    self._window[cx + self._width*cy] = WinElem {_data: self.point_char(bits), _color: color, _invert: false};
+                }
+            }
+        }
+    }
+
+    /* the character of a cell with the points of the bits on. */
+    fn point_char(&self, bits: u8) -> char {
+        match self._raster_mode {
+            RasterMode::Braille => char::from_u32(BRAILLE_BASE + bits as u32).unwrap_or(PRINT_CHAR),
+            _ => match bits & 0b11 {
+                0b01 => '▀',
+                0b10 => '▄',
+                _ => '█',
+            },
+        }
+    }
+
+    /* the terminal cleared and all the Window, the color (and the reverse video) is changed only
+     * when it is different from the last one. */
+    fn full_frame(&mut self) -> String {
+        let mut result: String = format!("{}{}", termion::clear::All, termion::cursor::Goto(1, 1));
+        let mut color: Option<Rgb> = None;
+        let mut invert: Option<bool> = None;
         for i in 0..self._height {
             for j in 0..self._width {
-                self._Window[i*self._width + j] = WinElem{_data: character, _color: WHITE_COLOR};
+                let elem = &self._window[i*self._width + j];
+                if color != Some(elem._color) {
+                    result.push_str(&Fg(elem._color).to_string());
+                    color = Some(elem._color);
+                }
+                if invert != Some(elem._invert) {
+                    result.push_str(style_of(elem._invert));
+                    invert = Some(elem._invert);
+                }
+                result.push(elem._data);
             }
+            result.push_str("\r\n");
         }
+        self._dirty = true;
+        result
     }
-    
-    pub fn print(&self) {
+
+    /* only the cells that changed since the last frame, moving the cursor when the changed cells
+     * are not next to each other. */
+    fn diff_frame(&mut self) -> String {
         let mut result: String = "".to_string();
+        if self._dirty {
+            result.push_str(termion::clear::All.as_ref());
+            self._presented.fill(EMPTY_CELL);
+            self._dirty = false;
+        }
+        let mut color: Option<Rgb> = None;
+        let mut invert: Option<bool> = None;
+        let mut cursor: Option<(usize, usize)> = None;
         for i in 0..self._height {
             for j in 0..self._width {
-                let elem = &self._Window[i*self._width + j];
-                result.push_str(&format!("{}{}", Fg(elem._color), elem._data));
+                let pos = i*self._width + j;
+                let elem = &self._window[pos];
+                if *elem == self._presented[pos] {
+                    continue;
+                }
+                if cursor != Some((j, i)) {
+                    result.push_str(&termion::cursor::Goto(j as u16 + 1, i as u16 + 1).to_string());
+                }
+                if color != Some(elem._color) {
+                    result.push_str(&Fg(elem._color).to_string());
+                    color = Some(elem._color);
+                }
+                if invert != Some(elem._invert) {
+                    result.push_str(style_of(elem._invert));
+                    invert = Some(elem._invert);
+                }
+                result.push(elem._data);
+                cursor = Some((j + 1, i));
+                self._presented[pos] = elem.clone();
             }
-            result.push_str("\n");
         }
-        print!("{}", result);
+        result
     }
 
-    /* To draw the Figure, first it will be moved to not intersect with the plane in which will be
-     * drawed. The plane will be (x, y), so the Figure will be moved along the z axis.*/
-    pub fn draw(&mut self, fig: &mut Figure) {
-        let hei: i64 = self._height as i64 / 2;
-        let wid: i64 = self._width as i64 / 2;
-        let iter = fig._lines.iter();
-        for line in iter {
-            // proyect the begin Vector in the plane of the scream (this part can be used in the
-            // general case).
-            let vec_b = line._begin.sum(fig._position);
-
-            let beg = self._plane_vec_1
-                .mul(vec_b.scalar_prod(self._plane_vec_1))
-                .sum(self._plane_vec_2.mul(vec_b.scalar_prod(self._plane_vec_2)));
-            
-            // proyect the end Vector in the plane of the scream (this part can be used in the
-            // general case).
-            let vec_e = line._end.sum(fig._position);
-
-            let end = self._plane_vec_1
-                .mul(vec_e.scalar_prod(self._plane_vec_1))
-                .sum(self._plane_vec_2.mul(vec_e.scalar_prod(self._plane_vec_2)));
-
-            // draw Line in the Window, making small steps.
-            let mut coef1: f64 = beg.x;
-            let mut coef2: f64 = beg.y;
-            let mut acoef = 0f64;
-
-            while acoef < 1f64 {
-                let x = (coef1.round() as i64) + wid;
-                let y = (coef2.round() as i64) + hei;
-                if 0 <= x && x < self._width as i64 && 0 <= y && y < self._height as i64 {
-                    self._Window[x as usize + self._width*y as usize] = WinElem{_data: PRINT_CHAR, _color: fig._color};
+    /* cut the part of the Line that is nearer than the near plane. If all the Line is behind it,
+     * there is nothing to draw. */
+    fn clip_near(&self, beg: Vector, end: Vector) -> Option<(Vector, Vector)> {
+        let near = match self._projection {
+            Projection::Orthographic => return Some((beg, end)),
+            Projection::Perspective { near, .. } => near,
+        };
+        match (beg.z < near, end.z < near) {
+            (true, true) => None,
+            (false, false) => Some((beg, end)),
+            (beg_out, _) => {
+                let coef = (near - beg.z) / (end.z - beg.z);
+                let cut = beg + (end - beg) * coef;
+                if beg_out { Some((cut, end)) } else { Some((beg, cut)) }
+            },
+        }
+    }
+
+    /* cut the part of the face that is nearer than the near plane (the polygon keeps the order of
+     * its vertices). */
+    fn clip_face_near(&self, poly: &[Vector]) -> Vec<Vector> {
+        let near = match self._projection {
+            Projection::Orthographic => return poly.to_vec(),
+            Projection::Perspective { near, .. } => near,
+        };
+        let mut result: Vec<Vector> = Vec::new();
+        for i in 0..poly.len() {
+            let cur = poly[i];
+            let next = poly[(i + 1) % poly.len()];
+            if cur.z >= near {
+                result.push(cur);
+            }
+            if (cur.z < near) != (next.z < near) {
+                let coef = (near - cur.z) / (next.z - cur.z);
+                result.push(cur + (next - cur) * coef);
+            }
+        }
+        result
+    }
+
+    /* proyect a Vector (in view coordinates) in the Window, the result is in points (cells in
+     * Cell mode). One unit is the width of a cell, so the y coordinate is divided by the aspect of
+     * the cells. The third coordinate is the depth (the smaller the nearer), and it changes
+     * linearly along the scream so it can be interpolated between the proyected points. */
+    fn project(&self, vect: Vector) -> Vector {
+        self.screen_matrix().transform_point(vect)
+    }
+
+    /* the matrix of project. In perspective mode the fourth row gives the depth, so the result
+     * is divided by it, and the third row gives -1 (that is -1/z after the division). */
+    fn screen_matrix(&self) -> Matrix4 {
+        let hei = (self._height as i64 / 2) as f64;
+        let wid = (self._width as i64 / 2) as f64;
+        let aspect = self._cell_aspect;
+        let cell = match self._projection {
+            Projection::Orthographic => Matrix4 {
+                m : [
+                    [1f64, 0f64, 0f64, wid],
+                    [0f64, 1f64 / aspect, 0f64, hei],
+                    [0f64, 0f64, 1f64, 0f64],
+                    [0f64, 0f64, 0f64, 1f64],
+                ],
+            },
+            Projection::Perspective { focal, .. } => Matrix4 {
+                m : [
+                    [focal, 0f64, wid, 0f64],
+                    [0f64, focal / aspect, hei, 0f64],
+                    [0f64, 0f64, 0f64, -1f64],
+                    [0f64, 0f64, 1f64, 0f64],
+                ],
+            },
+        };
+
+        // the center of the cell is in the middle of its points.
+        let (sx, sy) = self.sub_cells();
+        let points = Vector {x: sx as f64, y: sy as f64, z: 1f64};
+        let center = Vector {x: (sx - 1) as f64 / 2f64, y: (sy - 1) as f64 / 2f64, z: 0f64};
+        Matrix4::translation(center) * Matrix4::scale(points) * cell
+    }
+
+    /* put the element in the point if nothing nearer is there. The point must be inside the
+     * Window. In the modes with more than one point by cell only the color is kept, and the point
+     * is off if the element is empty. */
+    fn plot(&mut self, x: usize, y: usize, depth: f64, elem: WinElem) {
+        let (gw, _) = self.grid_size();
+        let pos = x + gw*y;
+        if depth <= self._depth[pos] {
+            self._depth[pos] = depth;
+            if self._raster_mode == RasterMode::Cell {
+                self._window[pos] = elem;
+            } else {
+                self._dots[pos] = if elem._data == ' ' { None } else { Some(elem._color) };
+            }
+        }
+    }
+
+    /* the text is over everything, so its cells (all its points) are the nearest ones. */
+    fn mark_text(&mut self, x: usize, y: usize) {
+        let (sx, sy) = self.sub_cells();
+        let (gw, _) = self.grid_size();
+        for dy in 0..sy {
+            for dx in 0..sx {
+                let pos = x*sx + dx + gw*(y*sy + dy);
+                self._depth[pos] = f64::NEG_INFINITY;
+                if let Some(dot) = self._dots.get_mut(pos) {
+                    *dot = None;
                 }
+            }
+        }
+    }
 
-                coef1 = beg.x + acoef*(end.x - beg.x);
-                coef2 = beg.y + acoef*(end.y - beg.y);
-                acoef += LITTLE_CONSTANT;
+    /* cut the Line (in points) to the rectangle of the Window using the Liang-Barsky algorithm.
+     * If the Line is outside, there is nothing to draw. The rectangle includes all the positions
+     * that are rounded to a point of the Window. */
+    fn clip_rect(&self, beg: Vector, end: Vector) -> Option<(Vector, Vector)> {
+        let (gw, gh) = self.grid_size();
+        let min = -HALF_CELL;
+        let max_x = gw as f64 - 1f64 + HALF_CELL;
+        let max_y = gh as f64 - 1f64 + HALF_CELL;
+        let dx = end.x - beg.x;
+        let dy = end.y - beg.y;
+        let mut t0 = 0f64;
+        let mut t1 = 1f64;
+        for (p, q) in [(-dx, beg.x - min), (dx, max_x - beg.x), (-dy, beg.y - min), (dy, max_y - beg.y)] {
+            if p == 0f64 {
+                if q < 0f64 {
+                    return None;
+                }
+            } else {
+                let r = q / p;
+                if p < 0f64 {
+                    t0 = t0.max(r);
+                } else {
+                    t1 = t1.min(r);
+                }
             }
-            let x = (coef1.round() as i64) + wid;
-            let y = (coef2.round() as i64) + hei;
-            if 0 <= x && x < self._width as i64 && 0 <= y && y < self._height as i64 {
-                self._Window[x as usize + self._width*y as usize] = WinElem{_data: PRINT_CHAR, _color: fig._color}; 
+        }
+        if t0 > t1 {
+            return None;
+        }
+        let delta = end - beg;
+        Some((beg + delta * t0, beg + delta * t1))
+    }
+
+    /* draw the Line (in points) with a DDA, making one step by point along the longest side, so
+     * there are no holes and the short Lines take few steps. */
+    fn raster_line(&mut self, beg: Vector, end: Vector, elem: &WinElem) {
+        let (beg, end) = match self.clip_rect(beg, end) {
+            Some(clipped) => clipped,
+            None => return,
+        };
+        let delta = end - beg;
+        let steps = delta.x.abs().max(delta.y.abs()).ceil().max(1f64) as usize;
+        let step = delta * (1f64 / steps as f64);
+        let mut point = beg;
+        for _ in 0..=steps {
+            self.plot(point.x.round() as usize, point.y.round() as usize, point.z, elem.clone());
+            point = point + step;
+        }
+    }
+
+    /* call the function with each point (and its depth) covered by the triangle. */
+    fn fill_triangle(&mut self, tri: [Vector; 3], mut put: impl FnMut(&mut Window, usize, usize, f64)) {
+        let [a, b, c] = tri;
+        let area = (b.x - a.x)*(c.y - a.y) - (b.y - a.y)*(c.x - a.x);
+        if area.abs() < f64::EPSILON {
+            return;
+        }
+        let min_x = a.x.min(b.x).min(c.x).floor().max(0f64);
+        let (gw, gh) = self.grid_size();
+        let max_x = a.x.max(b.x).max(c.x).ceil().min(gw as f64 - 1f64);
+        let min_y = a.y.min(b.y).min(c.y).floor().max(0f64);
+        let max_y = a.y.max(b.y).max(c.y).ceil().min(gh as f64 - 1f64);
+        if min_x > max_x || min_y > max_y {
+            return;
+        }
+        let (min_x, max_x) = (min_x as usize, max_x as usize);
+        let (min_y, max_y) = (min_y as usize, max_y as usize);
+        for y in min_y..=max_y {
+            for x in min_x..=max_x {
+                let (px, py) = (x as f64, y as f64);
+                let wa = ((b.x - px)*(c.y - py) - (b.y - py)*(c.x - px)) / area;
+                let wb = ((c.x - px)*(a.y - py) - (c.y - py)*(a.x - px)) / area;
+                let wc = 1f64 - wa - wb;
+                if wa >= 0f64 && wb >= 0f64 && wc >= 0f64 {
+                    put(self, x, y, wa*a.z + wb*b.z + wc*c.z);
+                }
+            }
+        }
+    }
+
+    /* write the faces in the depth buffer, erasing what is behind them. */
+    fn hide_with_faces(&mut self, fig: &Figure, view: &[Vector]) {
+        for face in fig._faces.iter() {
+            let poly: Vec<Vector> = face.iter().map(|&i| view[i]).collect();
+            let poly: Vec<Vector> = self.clip_face_near(&poly).iter().map(|&v| self.project(v)).collect();
+            for i in 1..poly.len().saturating_sub(1) {
+                let tri = [poly[0], poly[i], poly[i + 1]];
+                let slope = depth_slope(tri);
+                self.fill_triangle(tri, |win, x, y, depth| {
+                    let depth = depth + slope + DEPTH_BIAS * depth.abs();
+                    win.plot(x, y, depth, EMPTY_CELL);
+                });
+            }
+        }
+    }
+
+    /* fill the faces with the characters of LUMINANCE (or with more points the brighter they are,
+     * if the Window has more than one point by cell). The normals are turned to the camera, so the
+     * order of the vertices of the faces don't matter. */
+    fn shade_faces(&mut self, fig: &Figure, view: &[Vector]) {
+        let light = self._camera.view_matrix().transform_direction(self._light).normalize();
+        for face in fig._faces.iter() {
+            let poly: Vec<Vector> = face.iter().map(|&i| view[i]).collect();
+            let mut normal = polygon_normal(&poly);
+            let sight = match self._projection {
+                Projection::Orthographic => Vector {x: 0f64, y: 0f64, z: 1f64},
+                Projection::Perspective { .. } => poly[0],
+            };
+            if normal.scalar_prod(sight) > 0f64 {
+                normal = -normal;
+            }
+            let bright = normal.scalar_prod(light).max(0f64);
+            let elem = WinElem {
+                _data: LUMINANCE[(bright * (LUMINANCE.len() - 1) as f64).round() as usize],
+                _color: fig._color,
+                _invert: false,
+            };
+            let cells = self._raster_mode == RasterMode::Cell;
+
+            let poly: Vec<Vector> = self.clip_face_near(&poly).iter().map(|&v| self.project(v)).collect();
+            for i in 1..poly.len().saturating_sub(1) {
+                self.fill_triangle([poly[0], poly[i], poly[i + 1]], |win, x, y, depth| {
+                    if cells || bright > DITHER[y % 4][x % 4] {
+                        win.plot(x, y, depth, elem.clone());
+                    } else {
+                        win.plot(x, y, depth, WinElem {_data: ' ', _color: elem._color, _invert: false});
+                    }
+                });
             }
         }
     }
 
-    pub fn write (&mut self, text: &str, ph: f64, pw: f64, mode: Writemodes, color: &'static dyn Color) {
+    /* To draw the Figure, the vertices are rotated and moved to the position of the Figure, seen
+     * from the camera, proyected in the plane of the scream and then the edges (or the faces in
+     * Solid mode) are drawed. In perspective mode, the parts that are behind the near plane are not
+     * drawed. The Figures without faces are always drawed with its edges.*/
+    pub fn draw(&mut self, fig: &mut Figure) {
+        self.draw_placed(fig, Matrix4::identity());
+    }
+
+    /* draw all the Figures of the Scene, each one moved by the transforms of its parents. */
+    pub fn draw_scene(&mut self, scene: &Scene) {
+        let parents = scene.parent_matrices();
+        for (node, parent) in scene.get_nodes().iter().zip(parents) {
+            self.draw_placed(node.get_figure(), parent);
+        }
+    }
+
+    /* draw the Figure with its transform applied after the parent one. */
+    fn draw_placed(&mut self, fig: &Figure, parent: Matrix4) {
+        let model_view = self._camera.view_matrix() * parent * fig._transform.matrix();
+        let view: Vec<Vector> = fig._vertices
+            .iter()
+            .map(|v| model_view.transform_point(*v))
+            .collect();
+
+        let mode = fig._draw_mode.unwrap_or(self._draw_mode);
+        if mode == DrawMode::Solid && !fig._faces.is_empty() {
+            self.shade_faces(fig, &view);
+            return;
+        }
+        if mode == DrawMode::HiddenLine {
+            self.hide_with_faces(fig, &view);
+        }
+
+        let elem = WinElem{_data: PRINT_CHAR, _color: fig._color, _invert: false};
+        for &(b, e) in fig._edges.iter() {
+            let (vec_b, vec_e) = match self.clip_near(view[b], view[e]) {
+                Some(clipped) => clipped,
+                None => continue,
+            };
+            self.raster_line(self.project(vec_b), self.project(vec_e), &elem);
+        }
+    }
+
+    /* write the text in the row at the fraction ph of the height. The fraction pw of the width is
+     * the center of the text (Centered), where the text begins (ToLeft) or where it ends
+     * (ToRight). The characters that don't fit in the Window are not written. */
+    pub fn write (&mut self, text: &str, ph: f64, pw: f64, mode: Writemodes, color: Rgb) {
+        self.write_text(text, ph, pw, mode, color, false);
+    }
+
+    /* like write, but the text is printed in reverse video (to highlight it). */
+    pub fn write_inverted (&mut self, text: &str, ph: f64, pw: f64, mode: Writemodes, color: Rgb) {
+        self.write_text(text, ph, pw, mode, color, true);
+    }
+
+    fn write_text (&mut self, text: &str, ph: f64, pw: f64, mode: Writemodes, color: Rgb, invert: bool) {
         let hegiht: usize = ((self._height as f64) * ph) as usize;
         let width: usize = ((self._width as f64) * pw) as usize;
         let length: usize = text.len();
         let textc: &[u8] = text.as_bytes();
-        match mode {
+        if hegiht >= self._height {
+            return;
+        }
+        let start: i64 = match mode {
             Writemodes::Centered => {
                 let lenby2: usize = length / 2usize;
-                if lenby2 <= width / 2 && true {
-                    for i in 0..length {
-                        let j = i + width - lenby2 + 1;
-                        self._Window[j + self._width*hegiht] = WinElem {_data: textc[i] as char, _color: color};
-                    }
+                if lenby2 > width {
+                    return;
                 }
+                (width - lenby2 + 1) as i64
             },
-            Writemodes::ToLeft => {
+            Writemodes::ToLeft => width as i64,
+            Writemodes::ToRight => width as i64 - length as i64,
+        };
+        for (i, c) in textc.iter().enumerate() {
+            let j = start + i as i64;
+            if j < 0 {
+                continue;
+            }
+            let j = j as usize;
+            if j >= self._width {
+                break;
+            }
+            self._window[j + self._width*hegiht] = WinElem {_data: *c as char, _color: color, _invert: invert};
+            self.mark_text(j, hegiht);
+        }
+    }
+}
 
-            },
-            Writemodes::ToRight=> {
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    static EPSILON: f64 = 1e-9;
+
+    fn close(a: Vector, b: Vector) -> bool {
+        (a - b).length() < EPSILON
+    }
+
+    #[test]
+    fn fill_and_write_to_the_right() {
+        let mut win = Window::get_new(2, 10);
+        win.fill('.');
+        // the text ends where the fraction of the width says, the last column here.
+        win.write("ab", 0f64, 1f64, Writemodes::ToRight, WHITE_COLOR);
+        assert_eq!(win.get_row(0).0, "........ab");
+        // what doesn't fit at the left is not written.
+        win.write("abcd", 0.5f64, 0.2f64, Writemodes::ToRight, WHITE_COLOR);
+        assert_eq!(win.get_row(1).0, "cd........");
+    }
+
+    #[test]
+    fn diff_prints_only_the_changes() {
+        let mut win = Window::get_new(3, 10);
+        // the first frame clears the terminal and prints everything.
+        assert!(win.diff_frame().starts_with(&termion::clear::All.to_string()));
+        assert_eq!(win.diff_frame(), "");
+
+        win.write("x", 0.5f64, 0.3f64, Writemodes::ToLeft, WHITE_COLOR);
+        let cell = format!("{}{}{}x", termion::cursor::Goto(4, 2), Fg(WHITE_COLOR), style_of(false));
+        assert_eq!(win.diff_frame(), cell);
+        assert_eq!(win.diff_frame(), "");
+
+        // after a full frame the next diff starts again from a clear terminal.
+        win.set_output_mode(OutputMode::FullRedraw);
+        assert_eq!(win.get_output_mode(), OutputMode::FullRedraw);
+        win.full_frame();
+        win.set_output_mode(OutputMode::Diff);
+        assert!(win.diff_frame().starts_with(&termion::clear::All.to_string()));
+    }
+
+    fn line(x0: f64, y0: f64, x1: f64, y1: f64) -> (Vector, Vector) {
+        (Vector {x: x0, y: y0, z: 1f64}, Vector {x: x1, y: y1, z: 1f64})
+    }
+
+    #[test]
+    fn far_lines_are_clipped_to_the_border() {
+        let elem = WinElem {_data: '#', _color: WHITE_COLOR, _invert: false};
+        let mut win = Window::get_new(4, 8);
+        let (beg, end) = line(-1e9f64, 1f64, 1e9f64, 1f64);
+        win.raster_line(beg, end, &elem);
+        assert_eq!(win.get_row(1).0, "########");
+
+        // the diagonal crosses the window from corner to corner.
+        let (beg, end) = line(-1e9f64, -1e9f64, 1e9f64, 1e9f64);
+        win.raster_line(beg, end, &elem);
+        assert_eq!(win.get_row(0).0, "#       ");
+        assert_eq!(win.get_row(3).0, "   #    ");
+
+        // a line that only passes near the window draws nothing.
+        win.clear();
+        let (beg, end) = line(-1e9f64, -1f64, 1e9f64, -1f64);
+        win.raster_line(beg, end, &elem);
+        assert!((0..4).all(|row| win.get_row(row).0.trim().is_empty()));
+    }
+
+    #[test]
+    fn vertical_line_on_the_last_column() {
+        let elem = WinElem {_data: '#', _color: WHITE_COLOR, _invert: false};
+        let mut win = Window::get_new(3, 5);
+        let (beg, end) = line(4f64, 0f64, 4f64, 2f64);
+        win.raster_line(beg, end, &elem);
+        for row in 0..3 {
+            assert_eq!(win.get_row(row).0, "    #");
+        }
+    }
+
+    #[test]
+    fn nearer_edge_wins() {
+        let far = WinElem {_data: 'f', _color: WHITE_COLOR, _invert: false};
+        let near = WinElem {_data: 'n', _color: WHITE_COLOR, _invert: false};
+        let mut win = Window::get_new(1, 6);
+        let deep = |x: f64, z: f64| Vector {x, y: 0f64, z};
+        // the far one is drawed before and after the near one, and the near one is kept.
+        win.raster_line(deep(0f64, 10f64), deep(5f64, 10f64), &far);
+        win.raster_line(deep(2f64, 5f64), deep(3f64, 5f64), &near);
+        win.raster_line(deep(0f64, 10f64), deep(5f64, 10f64), &far);
+        assert_eq!(win.get_row(0).0, "ffnnff");
+    }
+
+    #[test]
+    fn rotation_matrix_is_the_quaternion() {
+        let quat = Quaternion::from_euler(0.7f64, -2.1f64, 1.3f64);
+        for point in [Vector {x: 1f64, y: 0f64, z: 0f64}, Vector {x: -3f64, y: 2f64, z: 5f64}] {
+            assert!(close(Matrix4::rotation(quat).transform_point(point), quat.rotate(point)));
+        }
+    }
+
+    #[test]
+    fn square_is_a_closed_face() {
+        let square = Figure::square(4, 6, Vector {x: 0f64, y: 0f64, z: 0f64}, WHITE_COLOR);
+        assert_eq!(square.get_vertices().len(), 4);
+        assert!(square.get_vertices().iter().all(|v| v.x.abs() == 3f64 && v.y.abs() == 2f64));
+        assert_eq!(square.get_edges(), &[(0, 1), (1, 2), (2, 3), (3, 0)]);
+        assert_eq!(square.get_faces(), &[vec![0, 1, 2, 3]]);
+    }
+
+    #[test]
+    fn transform_applies_its_matrix() {
+        let transform = Transform {
+            translation: Vector {x: 1f64, y: 2f64, z: 3f64},
+            rotation: Quaternion::from_euler(0.3f64, -1.2f64, 2f64),
+            scale: Vector {x: 2f64, y: 0.5f64, z: 1f64},
+        };
+        let point = Vector {x: -4f64, y: 5f64, z: 0.5f64};
+        let scaled = Vector {x: -8f64, y: 2.5f64, z: 0.5f64};
+        let expected = transform.rotation.rotate(scaled) + transform.translation;
+        assert!(close(transform.apply(point), expected));
+
+        // the Figure keeps the pose given, with the rotation normalized.
+        let mut fig = Figure::cube(2f64, Vector {x: 0f64, y: 0f64, z: 0f64}, WHITE_COLOR);
+        fig.set_transform(Transform {rotation: Quaternion {w: 2f64, x: 0f64, y: 0f64, z: 0f64}, ..transform});
+        assert!(close(fig.get_position(), transform.translation));
+        assert!(close(fig.get_scale(), transform.scale));
+        assert_eq!(fig.get_orientation().w, 1f64);
+        fig.set_euler(0.3f64, -1.2f64, 2f64);
+        assert!(close(fig.get_orientation().rotate(point), transform.rotation.rotate(point)));
+    }
+
+    #[test]
+    fn camera_moves() {
+        let eye = Vector {x: 0f64, y: 0f64, z: -10f64};
+        let target = Vector {x: 0f64, y: 0f64, z: 0f64};
+        let up = Vector {x: 0f64, y: -1f64, z: 0f64};
+        let mut camera = Camera::get_new(eye, target, up);
+
+        // the pan moves both to the right of the scream.
+        camera.pan(3f64, 0f64);
+        assert!(close(camera.get_eye() - eye, camera.get_target() - target));
+        assert!(close(camera.get_target(), Vector {x: 3f64, y: 0f64, z: 0f64}));
+
+        // the orbit keeps the distance to the target, and the dolly stops before the target.
+        camera.orbit(1f64, 0.5f64);
+        assert!(((camera.get_eye() - camera.get_target()).length() - 10f64).abs() < EPSILON);
+        camera.dolly(100f64);
+        let distance = (camera.get_eye() - camera.get_target()).length();
+        assert!(distance > 0f64 && distance < 10f64);
+    }
 
+    #[test]
+    fn field_of_view_fills_the_width() {
+        // a quarter of turn shows a width of twice the distance.
+        match Projection::from_fov(std::f64::consts::FRAC_PI_2, 80, 1f64) {
+            Projection::Perspective { focal, near } => {
+                assert!((focal - 40f64).abs() < EPSILON);
+                assert_eq!(near, 1f64);
             },
+            Projection::Orthographic => panic!("the projection is not perspective"),
         }
     }
 }